@@ -1,32 +1,25 @@
 //!
-//! In this example, we will create an actix-web server, and then use [serde_tran::Json] to
+//! In this example, we will create an actix-web server, and then use [serde_tran::actix::Tran] to
 //! 1. accept Json body
 //! 2. throw Json response
 //!
+//! Run it with `cargo run --example actix-web-server --features actix-web`.
+//!
 //! The example creates an endpoint, POST '/login'.
 //!
 //! HTTP client sends username and password with `application/json` content-type, (see [LoginRequest])
-//! and then the HTTP client will return `application/json` response. (see [HelloWorldResponse])
-//!
-//! In production, **usually** we (as the server side) return something like this
-//! (see [MyResponse], the difference is, we replace the `data` field with a fixed struct [serde_tran::Json], instead of type `T`):
+//! and then the server responds with the content type `application/vnd.serde-tran+json`. (see [HelloWorldResponse])
 //!
-//! ```json
-//! {
-//!     "data": {
-//!         "message": "Hello, world!"
-//!     },
-//!     "code": 200, // the http code
-//!     "error": null
-//! }
-//! ```
+//! Both of the request body and the response body are [serde_tran::Json] envelopes, and
+//! [serde_tran::actix::Tran] converts them from/to the structs. If the request body is not a
+//! valid envelope, the server responds 400 with the error message.
 //!
 //! Access this endpoint by `curl`:
 //! use a new terminal and run (username = "serde_tran:username", password = "serde_tran:password:123456"):
 //! ```bash
 //! curl -X POST http://127.0.0.1:8080/login -H "Content-Type: application/json" -d '{"f":"base64","v":"NgAAAAAAAAATAAAAAAAAAHNlcmRlX3RyYW46dXNlcm5hbWUTAAAAAAAAAHNlcmRlX3RyYW46cGFzc3dvcmRnmz7nMG94SA"}'
 //!
-//! > {"data":{"f":"base64","v":"FQAAAAAAAAANAAAAAAAAAEhlbGxvLCB3b3JsZCEgHUu5szXXFQ"},"code":201,"error":null}
+//! > {"f":"base64","v":"VFJBTgEADQAAAAAAAABIZWxsbywgd29ybGQhgu1Y5gOtx50"}
 //! ```
 //!
 //! > the body of this request is from [generate_login_request_body]
@@ -60,10 +53,6 @@ pub async fn main() -> anyhow::Result<()> {
 // this is how we defined structs in this example
 mod data_structure {
     use serde::{Deserialize, Serialize};
-    use serde_tran::Json;
-
-    /// All Http Request is [serde_tran::Json]
-    pub type Request = Json;
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct LoginRequest {
@@ -71,30 +60,6 @@ mod data_structure {
         pub password: String,
     }
 
-    /// [MyResponse] is the HTTP response body struct, which we throw to the HTTP client.
-    #[derive(Serialize, Debug, Clone)]
-    pub struct MyResponse {
-        /// Note: use [serde_tran::Json] here
-        pub data: serde_tran::Json,
-        /// HTTP status code
-        pub code: u16,
-        /// Note: in this example, just return None ( json `null` )
-        pub error: Option<()>,
-    }
-
-    impl MyResponse {
-        /// new a [Self].
-        ///
-        /// note: the first argument (`my_data`) is your custom struct (maybe [HelloWorldResponse])
-        pub fn new<T: Serialize>(my_data: &T, code: u16) -> Self {
-            Self {
-                data: serde_tran::to_json(my_data).unwrap(), // in practice, you need to handle this error. (consider using [anyhow::Result])
-                code,
-                error: None,
-            }
-        }
-    }
-
     /// A simple struct that just return { "message": "Hello, world!" }
     #[derive(Serialize, Debug, Clone)]
     pub struct HelloWorldResponse {
@@ -105,13 +70,15 @@ mod data_structure {
 // methods that we handle HTTP actix-web server
 mod start_server {
     use actix_web::web;
-    use actix_web::HttpResponse;
+    use serde_tran::actix::Tran;
 
     use super::*;
 
     pub async fn start(host: &str, port: u16) -> anyhow::Result<()> {
         actix_web::HttpServer::new(|| {
             actix_web::App::new()
+                // optional: the config used by Tran, for example to limit the untrusted payloads
+                .app_data(serde_tran::Config::new().limits(serde_tran::Limits::new().depth(32)))
                 .service(web::resource("/login").route(web::post().to(login)))
         })
             .bind(format!("{}:{}", host, port))?
@@ -121,18 +88,12 @@ mod start_server {
         unreachable!()
     }
 
-    async fn login(body: web::Json<Request>) -> HttpResponse {
-        println!("{:?}", &body);
-
-        // decode body into username and password:
-        let body: LoginRequest = body.to_value().unwrap(); // note: handle this error in production
+    async fn login(Tran(body): Tran<LoginRequest>) -> Tran<HelloWorldResponse> {
+        // the body is decoded into username and password
         println!("username = {}, password = {}", &body.username, &body.password);
 
-        let hello_world = HelloWorldResponse {
+        Tran(HelloWorldResponse {
             message: "Hello, world!",
-        };
-
-        let resp = MyResponse::new(&hello_world, 201);
-        HttpResponse::Accepted().json(&resp)
+        })
     }
 }
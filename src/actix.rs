@@ -0,0 +1,313 @@
+//! [actix-web](https://docs.rs/actix-web/4) integration (feature `actix-web`).
+//!
+//! [Tran] extracts `T` from a request body which is a [crate::Json] envelope (or a [crate::Binary]
+//! envelope, by the content type), and responds `T` in the envelope the client prefers (see
+//! [Config::to_body]). The [Config] is read from the app data (`App::new().app_data(config)`), or the
+//! default config is used.
+//!
+//! ```rust,no_run
+//! use actix_web::{web, App, HttpServer};
+//! use serde::{Deserialize, Serialize};
+//! use serde_tran::actix::Tran;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct LoginRequest { username: String, password: String }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct LoginResponse { message: String }
+//!
+//! async fn login(Tran(request): Tran<LoginRequest>) -> Tran<LoginResponse> {
+//!     Tran(LoginResponse { message: format!("hello, {}", request.username) })
+//! }
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! HttpServer::new(|| App::new().route("/login", web::post().to(login)))
+//!     .bind("127.0.0.1:8080")?
+//!     .run()
+//!     .await
+//! # }
+//! ```
+//!
+//! [Transparent] is a middleware for the existing handlers of plain JSON, it unwraps the envelopes
+//! in the request bodies, and wraps the JSON response bodies into envelopes.
+
+use std::future::{Future, Ready, ready};
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::rc::Rc;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::web::Bytes;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::{Deserialize, Serialize};
+use crate::{Accept, Config, MediaType, error};
+
+/// [Tran] is an extractor of the data in a [crate::Json] envelope, and a responder which puts the
+/// data in an envelope.
+///
+/// the body of the request is limited by [actix_web::web::PayloadConfig] (256 KB by default), and
+/// the [crate::Limits] of the [Config] in the app data.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Tran<T>(pub T);
+
+impl<T> Tran<T> {
+    /// take the data out
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Tran<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Tran<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// the [Config] in the app data, or the default one
+fn config_of(req: &HttpRequest) -> Config {
+    req.app_data::<Config>().cloned().unwrap_or_default()
+}
+
+fn content_type_of(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok())
+}
+
+fn accept_of(headers: &HeaderMap) -> Accept {
+    Accept::parse_all(headers.get_all(header::ACCEPT).filter_map(|value| value.to_str().ok()))
+}
+
+impl<T> FromRequest for Tran<T>
+    where T: for<'de> Deserialize<'de> + 'static
+{
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = config_of(req);
+        let content_type = content_type_of(req.headers()).map(str::to_string);
+        let bytes = Bytes::from_request(req, payload);
+
+        Box::pin(async move {
+            let bytes = bytes.await.map_err(body_error)?;
+            Ok(Tran(config.from_body(content_type.as_deref(), &bytes)?))
+        })
+    }
+}
+
+impl<T> Responder for Tran<T>
+    where T: Serialize
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        let config = config_of(req);
+
+        match config.to_body(&self.0, &accept_of(req.headers())) {
+            Ok((body, media_type)) => HttpResponse::Ok().content_type(media_type.to_string()).body(body),
+            Err(err) => err.error_response(),
+        }
+    }
+}
+
+/// errors are responded with the status code, and a JSON body
+/// `{"code": "...", "stage": "...", "path": "...", "message": "..."}` (see the `Serialize` of
+/// [error::Error]): 500 when encoding fails, 413 when a [crate::Limits] is exceeded, and 400 for
+/// other decoding errors.
+impl ResponseError for error::Error {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        json_error(self.status_code(), serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// the errors of reading the body are responded like [error::Error]
+fn body_error(err: actix_web::Error) -> actix_web::Error {
+    let status = err.as_response_error().status_code();
+    let body = error::Error::http_body_of(status.as_u16(), err.to_string());
+    actix_web::error::InternalError::from_response(err, json_error(status, body)).into()
+}
+
+fn json_error(status: StatusCode, body: String) -> HttpResponse<BoxBody> {
+    HttpResponse::build(status).content_type("application/json").body(body)
+}
+
+/// the header which opts in to [Transparent]: the client sends it when the request body is an
+/// envelope or the response body should be one, and the bodies of the response with it are envelopes.
+pub const TRAN_HEADER: HeaderName = HeaderName::from_static("x-serde-tran");
+
+/// [Transparent] is a middleware which unwraps the [crate::Json] envelopes in the request bodies
+/// into plain JSON before the handler runs, and wraps the JSON response bodies (with content type
+/// `application/json`) into envelopes, so the existing handlers (`web::Json<T>`,
+/// `HttpResponse::Ok().json(...)`) save space without any change.
+///
+/// it applies to the routes it wraps: the request bodies with the content type [crate::MediaType]
+/// or the [TRAN_HEADER] are unwrapped, and the responses to the requests with the [TRAN_HEADER] or
+/// which prefer envelopes ([crate::Accept::prefers_tran], or [crate::Accept::prefers_binary] for
+/// the [crate::Binary] envelopes) are wrapped. The response which is wrapped has the [TRAN_HEADER],
+/// and the content type of the envelope. The JSON responses have `Vary: accept, x-serde-tran`.
+///
+/// the middleware does not know the types of the handlers, so the codec of the config should be
+/// self-describing ([crate::CodecId::is_self_describing]), and the clients should use it as well.
+///
+/// ```rust,no_run
+/// use actix_web::{web, App, HttpResponse};
+/// use serde_tran::{CodecId, Config};
+/// use serde_tran::actix::Transparent;
+///
+/// async fn hello(name: web::Json<String>) -> HttpResponse {
+///     HttpResponse::Ok().json(format!("hello, {}", name))
+/// }
+///
+/// let transparent = Transparent::new(Config::new().codec(CodecId::MessagePack)).unwrap();
+/// let app = App::new()
+///     .service(web::resource("/hello").wrap(transparent).route(web::post().to(hello)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Transparent {
+    config: Config,
+    always: bool,
+}
+
+impl Transparent {
+    /// create the middleware, the codec of config should be self-describing
+    pub fn new(config: Config) -> error::Result<Self> {
+        config.check_self_describing()?;
+
+        Ok(Self { config, always: false })
+    }
+
+    /// wrap the responses to all the requests, not only the ones with the [TRAN_HEADER].
+    ///
+    /// the request bodies are still unwrapped only when they are envelopes, so the plain JSON
+    /// clients can send requests to the route as before.
+    #[inline]
+    pub fn always(mut self) -> Self {
+        self.always = true;
+        self
+    }
+
+    /// whether the request body is an envelope
+    fn unwraps(&self, headers: &HeaderMap) -> bool {
+        let is_tran = content_type_of(headers).and_then(MediaType::from_content_type).is_some();
+        is_tran || headers.contains_key(TRAN_HEADER)
+    }
+
+    /// whether the response body should be an envelope
+    fn wraps(&self, headers: &HeaderMap, accept: &Accept) -> bool {
+        self.always || accept.prefers_tran() || accept.prefers_binary() || headers.contains_key(TRAN_HEADER)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Transparent
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+        B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = actix_web::Error;
+    type Transform = TransparentService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TransparentService {
+            service: Rc::new(service),
+            transparent: Rc::new(self.clone()),
+        }))
+    }
+}
+
+/// the service of [Transparent]
+pub struct TransparentService<S> {
+    service: Rc<S>,
+    transparent: Rc<Transparent>,
+}
+
+impl<S, B> Service<ServiceRequest> for TransparentService<S>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+        B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let transparent = self.transparent.clone();
+
+        Box::pin(async move {
+            let accept = accept_of(req.headers());
+            let wraps = transparent.wraps(req.headers(), &accept);
+
+            if transparent.unwraps(req.headers()) {
+                // the body is limited by actix_web::web::PayloadConfig
+                let body = match req.extract::<Bytes>().await {
+                    Ok(body) => body,
+                    Err(err) => return Ok(req.error_response(body_error(err))),
+                };
+                if !body.is_empty() {
+                    let body = match transparent.config.envelope_to_json(content_type_of(req.headers()), &body) {
+                        Ok(body) => body,
+                        Err(err) => return Ok(req.error_response(err)),
+                    };
+                    req.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                    req.headers_mut().remove(header::CONTENT_LENGTH);
+                    req.set_payload(Payload::from(body));
+                }
+            }
+
+            let mut res = service.call(req).await?;
+            let is_json = res.headers().get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("application/json"));
+            if !is_json {
+                return Ok(res.map_into_boxed_body());
+            }
+
+            // the response depends on the Accept and TRAN_HEADER headers of the request
+            res.headers_mut().append(header::VARY, HeaderValue::from_static("accept, x-serde-tran"));
+            if !wraps {
+                return Ok(res.map_into_boxed_body());
+            }
+
+            let (req, res) = res.into_parts();
+            let (mut res, body) = res.into_parts();
+            let body = actix_web::body::to_bytes(body).await
+                .map_err(|err| actix_web::error::ErrorInternalServerError(err.into()))?;
+
+            // the body which is not valid JSON is responded as is
+            let body = match transparent.config.json_to_envelope(&body, &accept) {
+                Ok((wrapped, media_type)) => {
+                    res.headers_mut().insert(TRAN_HEADER, HeaderValue::from_static("1"));
+                    if let Ok(value) = HeaderValue::try_from(media_type.to_string()) {
+                        res.headers_mut().insert(header::CONTENT_TYPE, value);
+                    }
+                    Bytes::from(wrapped)
+                },
+                Err(_) => body,
+            };
+            res.headers_mut().remove(header::CONTENT_LENGTH);
+
+            Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+        })
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use serde::{Deserialize, Serialize};
+use crate::{Base, Config, Limits, error};
+use crate::error::{ResultExt, Stage};
+
+const BEGIN: &str = "-----BEGIN SERDE TRAN-----";
+const END: &str = "-----END SERDE TRAN-----";
+/// the max length of the base64 lines
+const LINE_WIDTH: usize = 64;
+
+/// [Armored] is the ascii-armored text of a payload, which survives tickets, emails and config files:
+///
+/// ```text
+/// -----BEGIN SERDE TRAN-----
+/// Comment: the order of alice
+///
+/// VFJBTgEABQAAAAAAAABhbGljZSoAAACB4FfWrizGdQ==
+/// =TqHw
+/// -----END SERDE TRAN-----
+/// ```
+///
+/// the `Key: value` headers are optional, the payload (the bytes of [crate::to_vec]) is encoded in
+/// base64 lines of 64 characters, followed by the CRC-24 checksum line (as in OpenPGP).
+///
+/// the parser ([Armored::from_str]) ignores the text around the armor, and the whitespace around the lines.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Armored {
+    headers: Vec<(String, String)>,
+    payload: Vec<u8>,
+}
+
+impl Armored {
+    /// create [Armored] from the bytes of [crate::to_vec]
+    #[inline]
+    pub(crate) fn from_payload(payload: Vec<u8>) -> Self {
+        Self {
+            headers: Vec::new(),
+            payload,
+        }
+    }
+
+    /// add a `Key: value` header, the line breaks are replaced with spaces (and `:` in the key with `-`)
+    pub fn header<K: AsRef<str>, V: AsRef<str>>(mut self, key: K, value: V) -> Self {
+        let key = key.as_ref().trim().replace([':', '\r', '\n'], "-");
+        let value = value.as_ref().trim().replace(['\r', '\n'], " ");
+        self.headers.push((key, value));
+        self
+    }
+
+    /// the headers in order
+    #[inline]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// the value of the first header with the key
+    pub fn get_header(&self, key: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+
+    /// the payload, which is the bytes of [crate::to_vec]
+    #[inline]
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// convert [Self] back to user data T
+    pub fn to_value<T>(&self) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        self.to_value_with(&Config::default())
+    }
+
+    /// convert [Self] back to user data T, with the [crate::Limits] in config
+    pub fn to_value_with<T>(&self, config: &Config) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        config.from_slice(&self.payload)
+    }
+
+    /// parse the armored text, [Limits::decoded_length] is checked before decoding
+    fn parse(text: &str, limits: &Limits) -> error::Result<Self> {
+        let not_found = |line| error::ErrorKind::CustomError(format!("{} is not found", line));
+
+        let (_, text) = text.split_once(BEGIN).ok_or_else(|| not_found(BEGIN)).at(Stage::EnvelopeParse)?;
+        let (text, _) = text.split_once(END).ok_or_else(|| not_found(END)).at(Stage::EnvelopeParse)?;
+
+        let mut headers = Vec::new();
+        let mut body = String::new();
+        let mut checksum = None;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if checksum.is_some() {
+                return Err(error::ErrorKind::CustomError("unexpected line after the checksum".to_string())).at(Stage::EnvelopeParse);
+            }
+
+            // base64 never contains ':', and the lines never start with '='
+            if let Some((key, value)) = line.split_once(':') {
+                if !body.is_empty() {
+                    return Err(error::ErrorKind::CustomError(format!("unexpected header {}", key))).at(Stage::EnvelopeParse);
+                }
+                headers.push((key.trim().to_string(), value.trim().to_string()));
+            } else if let Some(crc) = line.strip_prefix('=') {
+                checksum = Some(crc);
+            } else {
+                body.push_str(line);
+            }
+        }
+
+        limits.check_decoded_length(Base::Base64.decoded_length_bound(&body)).at(Stage::BaseDecode)?;
+        let payload = BASE64_STANDARD.decode(&body).at(Stage::BaseDecode)?;
+
+        let checksum = checksum
+            .ok_or_else(|| error::ErrorKind::CustomError("checksum line is not found".to_string()))
+            .at(Stage::EnvelopeParse)?;
+        let mut crc = [0u8; 4];
+        let crc = match BASE64_STANDARD.decode_slice(checksum, &mut crc) {
+            Ok(3) => u32::from_be_bytes([0, crc[0], crc[1], crc[2]]),
+            _ => return Err(error::ErrorKind::CustomError(format!("invalid checksum line ={}", checksum))).at(Stage::EnvelopeParse),
+        };
+        let expected = crc24(&payload);
+        if crc != expected {
+            return Err(error::ErrorKind::HashError(expected as u64, crc as u64)).at(Stage::Checksum);
+        }
+
+        Ok(Self { headers, payload })
+    }
+}
+
+impl Display for Armored {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", BEGIN)?;
+        for (key, value) in &self.headers {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+        if !self.headers.is_empty() {
+            writeln!(f)?;
+        }
+
+        let body = BASE64_STANDARD.encode(&self.payload);
+        for line in body.as_bytes().chunks(LINE_WIDTH) {
+            // base64 is ascii
+            writeln!(f, "{}", String::from_utf8_lossy(line))?;
+        }
+
+        let crc = crc24(&self.payload).to_be_bytes();
+        writeln!(f, "={}", BASE64_STANDARD.encode(&crc[1..]))?;
+        write!(f, "{}", END)
+    }
+}
+
+impl FromStr for Armored {
+    type Err = error::Error;
+
+    /// parse the armored text, the text around the armor is ignored
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, &Limits::default())
+    }
+}
+
+impl Config {
+    /// convert given data into [Armored], see [to_armored]
+    pub fn to_armored<T>(&self, data: &T) -> error::Result<Armored>
+        where T: Serialize + ?Sized
+    {
+        let bytes = self.to_vec(data)?;
+        Ok(Armored::from_payload(bytes))
+    }
+
+    /// parse the armored text and convert it into T, see [from_armored]
+    pub fn from_armored<T>(&self, text: &str) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        Armored::parse(text, &self.limits)?.to_value_with(self)
+    }
+}
+
+/// convert given data into [Armored], use [Armored::header] to add headers, and
+/// `to_string` (see [Display]) to get the text.
+///
+/// ```rust
+/// let armored = serde_tran::to_armored(&("alice", 42)).unwrap()
+///     .header("Comment", "the order of alice");
+/// let text = armored.to_string();
+///
+/// let email = format!("Hi,\n\nplease check the order:\n\n{}\n\nThanks", text);
+/// let order: (String, u32) = serde_tran::from_armored(&email).unwrap();
+/// assert_eq!(order, ("alice".to_string(), 42));
+/// ```
+pub fn to_armored<T>(data: &T) -> error::Result<Armored>
+    where T: Serialize + ?Sized
+{
+    Config::default().to_armored(data)
+}
+
+/// parse the armored text and convert it into T, the text around the armor is ignored.
+/// use [Armored::from_str] to read the headers
+pub fn from_armored<T>(text: &str) -> error::Result<T>
+    where T: for<'de> Deserialize<'de>
+{
+    Config::default().from_armored(text)
+}
+
+/// CRC-24 of OpenPGP ASCII armor (RFC 4880, section 6.1)
+fn crc24(bytes: &[u8]) -> u32 {
+    const INIT: u32 = 0xb704ce;
+    const POLY: u32 = 0x1864cfb;
+
+    let mut crc = INIT;
+    for byte in bytes {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= POLY;
+            }
+        }
+    }
+
+    crc & 0xffffff
+}
@@ -0,0 +1,222 @@
+//! [axum](https://docs.rs/axum/0.8) integration (feature `axum`).
+//!
+//! [Tran] extracts `T` from a request body which is a [crate::Json] envelope (or a [crate::Binary]
+//! envelope, by the content type). The [Config] is read from the request
+//! extensions (`Router::new().layer(Extension(config))`), or the default config is used.
+//!
+//! **the [Tran] response is always encoded with the default [Config]**: a response does not see
+//! the request, so the codec, the secret and the other options in the request extensions do not
+//! apply, unlike the actix-web responder. A server configured otherwise should extract
+//! [Negotiate], which responds in the envelope the client prefers (see [Config::to_body]), encoded
+//! with the [Config] in the request extensions.
+//!
+//! ```rust,no_run
+//! use axum::{routing::post, Router};
+//! use serde::{Deserialize, Serialize};
+//! use serde_tran::axum::Tran;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct LoginRequest { username: String, password: String }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct LoginResponse { message: String }
+//!
+//! // the response is encoded with the default config, see Negotiate for the configured one
+//! async fn login(Tran(request): Tran<LoginRequest>) -> Tran<LoginResponse> {
+//!     Tran(LoginResponse { message: format!("hello, {}", request.username) })
+//! }
+//!
+//! let app: Router = Router::new().route("/login", post(login));
+//! ```
+//!
+//! ```rust,no_run
+//! use axum::{response::Response, routing::post, Router};
+//! use serde_tran::axum::{Negotiate, Tran};
+//!
+//! async fn hello(negotiate: Negotiate, Tran(name): Tran<String>) -> Response {
+//!     negotiate.respond(&format!("hello, {}", name))
+//! }
+//!
+//! let app: Router = Router::new().route("/hello", post(hello));
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::ops::{Deref, DerefMut};
+use ::axum::body::Bytes;
+use ::axum::extract::{FromRequest, FromRequestParts, Request};
+use ::axum::extract::rejection::BytesRejection;
+use ::axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use ::axum::http::request::Parts;
+use ::axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use crate::{Accept, Config, error};
+
+/// [Tran] is an extractor of the data in a [crate::Json] envelope, and a response which puts the
+/// data in an envelope.
+///
+/// the body of the request is limited by [axum::extract::DefaultBodyLimit] (2 MB by default), and
+/// the [crate::Limits] of the [Config] in the request extensions. The response is a [crate::Json]
+/// envelope encoded with the default [Config], use [Negotiate] to respond in the envelope the
+/// client prefers with the [Config] in the request extensions.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Tran<T>(pub T);
+
+impl<T> Tran<T> {
+    /// take the data out
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Tran<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Tran<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T, S> FromRequest<S> for Tran<T>
+    where
+        T: for<'de> Deserialize<'de>,
+        S: Send + Sync,
+{
+    type Rejection = TranRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let config = req.extensions().get::<Config>().cloned().unwrap_or_default();
+        let content_type = req.headers().get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let bytes = Bytes::from_request(req, state).await.map_err(TranRejection::Body)?;
+
+        Ok(Tran(config.from_body(content_type.as_deref(), &bytes)?))
+    }
+}
+
+impl<T> IntoResponse for Tran<T>
+    where T: Serialize
+{
+    fn into_response(self) -> Response {
+        respond(&Config::default(), &Accept::default(), &self.0)
+    }
+}
+
+/// respond data in the envelope the client prefers, with the content type of the envelope
+fn respond<T>(config: &Config, accept: &Accept, data: &T) -> Response
+    where T: Serialize + ?Sized
+{
+    match config.to_body(data, accept) {
+        Ok((body, media_type)) => ([(header::CONTENT_TYPE, media_type.to_string())], body).into_response(),
+        Err(err) => TranRejection::Tran(err).into_response(),
+    }
+}
+
+/// [Negotiate] is an extractor of the [Config] in the request extensions (or the default one) and
+/// the Accept headers of the request, which responds data in the envelope the client prefers:
+/// [crate::Binary] if [Accept::prefers_binary], or [crate::Json] otherwise (see [Config::to_body]).
+#[derive(Clone, Debug, Default)]
+pub struct Negotiate {
+    config: Config,
+    accept: Accept,
+}
+
+impl Negotiate {
+    /// respond data in the envelope the client prefers, with the content type of the envelope.
+    /// the response is [TranRejection::Tran] when encoding fails
+    pub fn respond<T>(&self, data: &T) -> Response
+        where T: Serialize + ?Sized
+    {
+        let mut res = respond(&self.config, &self.accept, data);
+        // the response depends on the Accept header of the request
+        res.headers_mut().append(header::VARY, HeaderValue::from_static("accept"));
+        res
+    }
+}
+
+impl<S> FromRequestParts<S> for Negotiate
+    where S: Send + Sync
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            config: parts.extensions.get::<Config>().cloned().unwrap_or_default(),
+            accept: accept_of(&parts.headers),
+        })
+    }
+}
+
+/// the Accept headers of the request
+fn accept_of(headers: &HeaderMap) -> Accept {
+    Accept::parse_all(headers.get_all(header::ACCEPT).iter().filter_map(|value| value.to_str().ok()))
+}
+
+/// [TranRejection] is the rejection of [Tran], and the response when encoding fails.
+///
+/// it is responded with a JSON body `{"code": "...", "stage": "...", "path": "...", "message": "..."}`
+/// (see the `Serialize` of [error::Error]), and the status code: 413 when a [crate::Limits] (or the
+/// body limit) is exceeded, 400 for other decoding errors, and 500 when encoding fails.
+#[derive(Debug)]
+pub enum TranRejection {
+    /// reading the body failed
+    Body(BytesRejection),
+    /// decoding or encoding failed
+    Tran(error::Error),
+}
+
+impl TranRejection {
+    /// the status code of the response
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Body(rejection) => rejection.status(),
+            Self::Tran(err) => StatusCode::from_u16(err.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+impl From<error::Error> for TranRejection {
+    #[inline]
+    fn from(err: error::Error) -> Self {
+        Self::Tran(err)
+    }
+}
+
+impl Display for TranRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Body(rejection) => Display::fmt(rejection, f),
+            Self::Tran(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for TranRejection {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Body(rejection) => Some(rejection),
+            Self::Tran(err) => Some(err),
+        }
+    }
+}
+
+impl IntoResponse for TranRejection {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = match self {
+            Self::Body(rejection) => error::Error::http_body_of(status.as_u16(), rejection.body_text()),
+            Self::Tran(err) => serde_json::to_string(&err).unwrap_or_default(),
+        };
+
+        (status, [(header::CONTENT_TYPE, HeaderValue::from_static("application/json"))], body).into_response()
+    }
+}
@@ -1,6 +1,7 @@
 use base64::Engine;
 use serde::{Deserialize, Serialize};
-use crate::error;
+use crate::{Config, error};
+use crate::error::{ResultExt, Stage};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Base {
@@ -16,14 +17,29 @@ impl Base {
         match *self {
             Self::Base58 => Self::base58(bytes),
             Self::Base64 => Self::base64(bytes),
-        }
+        }.at(Stage::Encode)
     }
 
     #[inline]
+    #[allow(clippy::wrong_self_convention)]
     pub(crate) fn from_base<T: AsRef<[u8]>>(&self, s: T) -> error::Result<Vec<u8>> {
         match *self {
             Self::Base58 => Self::from_base58(s),
             Self::Base64 => Self::from_base64(s),
+        }.at(Stage::BaseDecode)
+    }
+
+    /// the upper bound of the decoded length, without decoding the string
+    #[inline]
+    pub(crate) fn decoded_length_bound<T: AsRef<[u8]>>(&self, s: T) -> u64 {
+        let s = s.as_ref();
+        match *self {
+            // every leading '1' is a zero byte, others take log(58)/log(256) < 0.733 byte per char
+            Self::Base58 => {
+                let zeros = s.iter().take_while(|c| **c == b'1').count() as u64;
+                zeros + ((s.len() as u64 - zeros) * 733).div_ceil(1000)
+            },
+            Self::Base64 => (s.len() as u64 * 3) / 4,
         }
     }
 
@@ -31,12 +47,12 @@ impl Base {
     fn base58<T: AsRef<[u8]>>(bytes: T) -> error::Result<String> {
         #[cfg(feature = "bs58")]
         {
-            return Ok(bs58::encode(bytes).into_string());
+            Ok(bs58::encode(bytes).into_string())
         }
 
         #[cfg(not(feature = "bs58"))]
         {
-            return Err(error::ErrorKind::CustomError("feature bs58 is not enabled".to_string()));
+            Err(error::ErrorKind::FeatureDisabled("bs58").into())
         }
     }
 
@@ -44,12 +60,12 @@ impl Base {
     fn from_base58<T: AsRef<[u8]>>(s: T) -> error::Result<Vec<u8>> {
         #[cfg(feature = "bs58")]
         {
-            return Ok(bs58::decode(s).into_vec()?)
+            Ok(bs58::decode(s).into_vec()?)
         }
 
         #[cfg(not(feature = "bs58"))]
         {
-            return Err(error::ErrorKind::CustomError("feature bs58 is not enabled".to_string()));
+            Err(error::ErrorKind::FeatureDisabled("bs58").into())
         }
     }
 
@@ -57,12 +73,12 @@ impl Base {
     fn base64<T: AsRef<[u8]>>(bytes: T) -> error::Result<String> {
         #[cfg(feature = "base64")]
         {
-            return Ok(base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(bytes))
+            Ok(base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(bytes))
         }
 
         #[cfg(not(feature = "base64"))]
         {
-            return Err(error::ErrorKind::CustomError("feature base64 is not enabled".to_string()))
+            Err(error::ErrorKind::FeatureDisabled("base64").into())
         }
     }
 
@@ -70,12 +86,12 @@ impl Base {
     fn from_base64<T: AsRef<[u8]>>(s: T) -> error::Result<Vec<u8>> {
         #[cfg(feature = "base64")]
         {
-            return Ok(base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(s)?)
+            Ok(base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(s)?)
         }
 
         #[cfg(not(feature = "base64"))]
         {
-            return Err(error::ErrorKind::CustomError("feature base64 is not enabled".to_string()))
+            Err(error::ErrorKind::FeatureDisabled("base64").into())
         }
     }
 }
@@ -90,13 +106,66 @@ pub(crate) fn from_base<T: AsRef<[u8]>>(s: T, base: Base) -> error::Result<Vec<u
     base.from_base(s)
 }
 
+impl Config {
+    /// convert a base encoded string to T, [crate::Limits::decoded_length] is checked before decoding
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn from_base<T>(&self, s: &str, base: Base) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        let bytes = self.decode_base(s, base)?;
+        self.from_slice(&bytes)
+    }
+
+    /// decode a base encoded string to bytes, [crate::Limits::decoded_length] is checked before decoding
+    pub(crate) fn decode_base(&self, s: &str, base: Base) -> error::Result<Vec<u8>> {
+        self.limits.check_decoded_length(base.decoded_length_bound(s)).at(Stage::BaseDecode)?;
+
+        from_base(s, base)
+    }
+
+    #[cfg(feature = "base64")]
+    /// convert data from base64 string to T, see [from_base64]
+    pub fn from_base64<T>(&self, s: &str) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        self.from_base(s, Base::Base64)
+    }
+
+    #[cfg(feature = "bs58")]
+    /// convert data from base58 string to T, see [from_base58]
+    pub fn from_base58<T>(&self, s: &str) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        self.from_base(s, Base::Base58)
+    }
+}
+
+impl Config {
+    #[cfg(feature = "base64")]
+    /// convert data to base64 string, see [to_base64]
+    pub fn to_base64<T>(&self, data: &T) -> error::Result<String>
+        where T: Serialize + ?Sized
+    {
+        let bytes = self.to_vec(data)?;
+        to_base(bytes, Base::Base64)
+    }
+
+    #[cfg(feature = "bs58")]
+    /// convert data to base58 string, see [to_base58]
+    pub fn to_base58<T>(&self, data: &T) -> error::Result<String>
+        where T: Serialize + ?Sized
+    {
+        let bytes = self.to_vec(data)?;
+        to_base(bytes, Base::Base58)
+    }
+}
+
 #[cfg(feature = "base64")]
 /// convert data to base64 string
 pub fn to_base64<T>(data: &T) -> error::Result<String>
     where T: Serialize
 {
-    let bytes = crate::to_vec(data)?;
-    to_base(bytes, Base::Base64)
+    Config::default().to_base64(data)
 }
 
 #[cfg(feature = "base64")]
@@ -105,8 +174,7 @@ pub fn from_base64<T>(s: &str) -> error::Result<T>
     where
         T: for<'de> Deserialize<'de>,
 {
-    let bytes = from_base(s, Base::Base64)?;
-    crate::from_slice(&bytes)
+    Config::default().from_base64(s)
 }
 
 #[cfg(feature = "bs58")]
@@ -114,8 +182,7 @@ pub fn from_base64<T>(s: &str) -> error::Result<T>
 pub fn to_base58<T>(data: &T) -> error::Result<String>
     where T: Serialize
 {
-    let bytes = crate::to_vec(data)?;
-    to_base(bytes, Base::Base58)
+    Config::default().to_base58(data)
 }
 
 #[cfg(feature = "bs58")]
@@ -123,6 +190,5 @@ pub fn to_base58<T>(data: &T) -> error::Result<String>
 pub fn from_base58<T>(s: &str) -> error::Result<T>
     where T: for<'de> Deserialize<'de>
 {
-    let bytes = from_base(s, Base::Base58)?;
-    crate::from_slice(&bytes)
+    Config::default().from_base58(s)
 }
@@ -35,25 +35,23 @@ mod tests {
         pub fn create_batches(n: usize, string_len: usize) -> Vec<Self> {
             let mut rng = thread_rng();
 
-            let data = (0..n).into_iter()
+            (0..n)
                 .map(|_| {
                     A {
                         a: rng.gen(),
                         b: rng.gen(),
                         c: rng.gen(),
                         d: rng.gen(),
-                        e1: (0..string_len).into_iter().map(|_| rng.gen()).collect(),
+                        e1: (0..string_len).map(|_| rng.gen()).collect(),
                         e2: Self::random_string(&mut rng, string_len),
-                        e3: (0..string_len).into_iter().map(|_| rng.gen()).collect(),
+                        e3: (0..string_len).map(|_| rng.gen()).collect(),
                         long_key_11111111111111111111111111: Self::random_string(&mut rng, string_len),
                         long_key_2222222222222222222222222222222: Self::random_string(&mut rng, string_len),
                         long_key_333333333333: Self::random_string(&mut rng, string_len),
                         long_key_4x4x4x4x4x4x4x4x4x4x44x4x44x4x444x4x: Self::random_string(&mut rng, string_len),
                     }
                 })
-                .collect();
-
-            data
+                .collect()
         }
     }
 
@@ -63,8 +61,8 @@ mod tests {
     }
 
     #[inline]
-    fn tran_encode<T: Serialize>(a: &T) -> String {
-        crate::to_json(a).unwrap().to_string().unwrap()
+    fn tran_encode<T: Serialize>(config: &crate::Config, a: &T) -> String {
+        config.to_json(a).unwrap().to_string().unwrap()
     }
 
     #[test]
@@ -79,7 +77,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn benchmark_codecs() {
+        let codecs = [
+            crate::CodecId::Bincode,
+            #[cfg(feature = "rmp-serde")]
+            crate::CodecId::MessagePack,
+            #[cfg(feature = "postcard")]
+            crate::CodecId::Postcard,
+            #[cfg(feature = "bitcode")]
+            crate::CodecId::Bitcode,
+            #[cfg(feature = "ciborium")]
+            crate::CodecId::Cbor,
+        ];
+
+        for codec in codecs {
+            let config = crate::Config::new().codec(codec);
+            for (n, string_len) in [(100, 10), (1024, 200)] {
+                benchmark_n_len_with(&config, n, string_len);
+            }
+        }
+    }
+
     fn benchmark_n_len(n: usize, string_len: usize) {
+        benchmark_n_len_with(&crate::Config::default(), n, string_len)
+    }
+
+    fn benchmark_n_len_with(config: &crate::Config, n: usize, string_len: usize) {
         const RUN: usize = 1;
 
         let mut json_time = 0u128;
@@ -99,7 +123,7 @@ mod tests {
             json_space += size;
 
             let start = time::SystemTime::now();
-            let size = tran_encode(&data).len();
+            let size = tran_encode(config, &data).len();
             let end = time::SystemTime::now();
 
             let diff = end.duration_since(start).unwrap().as_nanos();
@@ -112,6 +136,6 @@ mod tests {
         let json_sp = json_space as f64 / RUN as f64;
         let tran_sp = tran_space as f64 / RUN as f64;
 
-        println!("n={}, sl={}, \n\tjson.time={}ns/op, json.space={}bytes/bench, \n\ttran.time={}ns/op, tran.space={}bytes/bench\n\t[tran/json] time={}%, space={}%", n, string_len, json_op, json_sp, tran_op, tran_sp, (tran_op-json_op)/json_op*100.0, (tran_sp-json_sp)/json_sp *100.0);
+        println!("n={}, sl={}, codec={}, \n\tjson.time={}ns/op, json.space={}bytes/bench, \n\ttran.time={}ns/op, tran.space={}bytes/bench\n\t[tran/json] time={}%, space={}%", n, string_len, config.codec.name(), json_op, json_sp, tran_op, tran_sp, (tran_op-json_op)/json_op*100.0, (tran_sp-json_sp)/json_sp *100.0);
     }
 }
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use crate::{Config, error};
+
+/// [Binary] is the binary envelope: the bytes of [crate::to_vec], which record the codec and
+/// options, without any base encoding.
+///
+/// use it on the transports which are already binary (gRPC bytes fields, websocket binary frames,
+/// message queues), where the base encoding of [crate::Json] wastes about a third of the space.
+/// Over HTTP, its content type is [Binary::MEDIA_TYPE].
+///
+/// ```rust
+/// let binary = serde_tran::to_binary(&("alice", 42u32)).unwrap();
+/// let json = serde_tran::to_json(&("alice", 42u32)).unwrap();
+/// assert!(binary.as_bytes().len() < json.data().len());
+///
+/// let restored: (String, u32) = binary.to_value().unwrap();
+/// assert_eq!(restored, ("alice".to_string(), 42));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Binary {
+    payload: Vec<u8>,
+}
+
+impl Binary {
+    /// the media type of the [Binary] envelopes, see [crate::MediaType::binary]
+    pub const MEDIA_TYPE: &'static str = "application/vnd.serde-tran";
+
+    /// create [Binary] from the bytes of [crate::to_vec], the bytes are checked when decoding
+    #[inline]
+    pub fn from_vec(payload: Vec<u8>) -> Self {
+        Self { payload }
+    }
+
+    /// the bytes of the envelope
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// take the bytes of the envelope out
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.payload
+    }
+
+    /// convert [Self] back to user data T
+    pub fn to_value<T>(&self) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        self.to_value_with(&Config::default())
+    }
+
+    /// convert [Self] back to user data T, with the [crate::Limits] in config
+    pub fn to_value_with<T>(&self, config: &Config) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        config.from_slice(&self.payload)
+    }
+}
+
+impl AsRef<[u8]> for Binary {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+impl From<Binary> for Vec<u8> {
+    #[inline]
+    fn from(binary: Binary) -> Self {
+        binary.payload
+    }
+}
+
+impl Config {
+    /// convert given data into [Binary], see [to_binary]
+    pub fn to_binary<T>(&self, data: &T) -> error::Result<Binary>
+        where T: Serialize + ?Sized
+    {
+        Ok(Binary::from_vec(self.to_vec(data)?))
+    }
+}
+
+/// convert given data into [Binary], the binary envelope
+pub fn to_binary<T>(data: &T) -> error::Result<Binary>
+    where T: Serialize + ?Sized
+{
+    Config::default().to_binary(data)
+}
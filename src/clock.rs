@@ -0,0 +1,61 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// [Clock] tells the current time, which is used to issue and check the expiry of tokens.
+/// the default is [SystemClock], inject another one with [crate::Config::clock] (for example in tests).
+///
+/// any `Fn() -> SystemTime` is a clock.
+pub trait Clock: Send + Sync {
+    /// the current time
+    fn now(&self) -> SystemTime;
+}
+
+/// [SystemClock] is the default [Clock], which returns [SystemTime::now]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl<F> Clock for F
+    where F: Fn() -> SystemTime + Send + Sync
+{
+    #[inline]
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+/// [SharedClock] is the [Clock] in [crate::Config]
+#[derive(Clone)]
+pub(crate) struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    #[inline]
+    pub(crate) fn new<C: Clock + 'static>(clock: C) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    #[inline]
+    pub(crate) fn now(&self) -> SystemTime {
+        self.0.now()
+    }
+}
+
+impl Default for SharedClock {
+    #[inline]
+    fn default() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+impl Debug for SharedClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Clock")
+    }
+}
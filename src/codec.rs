@@ -0,0 +1,416 @@
+use std::io::Write;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use crate::{Limits, error};
+use crate::error::{ResultExt, Stage};
+
+/// [Codec] is the inner serialization format, which converts data into bytes (and back).
+///
+/// each codec has a [CodecId] and options, which are recorded in the payload, so the decoder
+/// always knows how to convert the bytes back. Choose a codec with [crate::Config::codec].
+///
+/// the trait is sealed: the payload records one of the ids of [CodecId], which every peer
+/// must understand, so the codecs are the ones of this crate.
+pub trait Codec: Default + sealed::Sealed {
+    /// the id recorded in the payload
+    const ID: CodecId;
+
+    /// the codec options recorded in the payload, only the lower 4 bits are available
+    #[inline]
+    fn options(&self) -> u8 {
+        0
+    }
+
+    /// restore the codec from the options recorded in the payload
+    #[inline]
+    fn from_options(options: u8) -> error::Result<Self> {
+        if options == 0 {
+            Ok(Self::default())
+        } else {
+            Err(error::ErrorKind::CustomError(format!("unknown options {} of codec {}", options, Self::ID.name())).into())
+        }
+    }
+
+    /// serialize data into the writer
+    fn serialize_into<W, T>(&self, writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized;
+
+    /// deserialize data from the bytes, the codec should apply the [Limits] it supports
+    fn deserialize<T>(&self, bytes: &[u8], limits: &Limits) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>;
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Bincode {}
+    #[cfg(feature = "rmp-serde")]
+    impl Sealed for super::MessagePack {}
+    #[cfg(feature = "postcard")]
+    impl Sealed for super::Postcard {}
+    #[cfg(feature = "bitcode")]
+    impl Sealed for super::Bitcode {}
+    #[cfg(feature = "ciborium")]
+    impl Sealed for super::Cbor {}
+}
+
+/// [CodecId] identifies a [Codec].
+///
+/// [Bincode], [Postcard] and [Bitcode] are compact but not self-describing, which means that
+/// types relying on `deserialize_any` (such as [serde_json::Value], `#[serde(untagged)]`,
+/// `#[serde(flatten)]` and internally tagged enums) can not be decoded. Use [MessagePack] or
+/// [Cbor] for them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum CodecId {
+    /// [Bincode], the default codec
+    #[default]
+    Bincode,
+    /// [MessagePack], requires feature `rmp-serde`
+    MessagePack,
+    /// [Postcard], requires feature `postcard`
+    Postcard,
+    /// [Bitcode], requires feature `bitcode`
+    Bitcode,
+    /// [Cbor], requires feature `ciborium`
+    Cbor,
+}
+
+impl CodecId {
+    /// the id in the lower 3 bits of the codec byte, the upper 4 bits are the codec options
+    #[inline]
+    pub(crate) fn id(&self) -> u8 {
+        match *self {
+            Self::Bincode => 0,
+            Self::MessagePack => 1,
+            Self::Postcard => 2,
+            Self::Bitcode => 3,
+            Self::Cbor => 4,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_id(id: u8) -> error::Result<Self> {
+        match id {
+            0 => Ok(Self::Bincode),
+            1 => Ok(Self::MessagePack),
+            2 => Ok(Self::Postcard),
+            3 => Ok(Self::Bitcode),
+            4 => Ok(Self::Cbor),
+            _ => Err(error::ErrorKind::CustomError(format!("unknown codec id {}", id)).into()),
+        }
+    }
+
+    /// the name of the codec, which is also the name of the feature that enables it
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Self::Bincode => "bincode",
+            Self::MessagePack => "rmp-serde",
+            Self::Postcard => "postcard",
+            Self::Bitcode => "bitcode",
+            Self::Cbor => "ciborium",
+        }
+    }
+
+    /// whether the codec is self-describing ([Self::MessagePack] and [Self::Cbor]), so the payload
+    /// can be decoded without knowing the type, such as into a `serde_json::Value`
+    #[inline]
+    pub fn is_self_describing(&self) -> bool {
+        matches!(*self, Self::MessagePack | Self::Cbor)
+    }
+
+    /// serialize data into the writer, using the [Codec] of this id with the options
+    pub(crate) fn serialize_into<W, T>(&self, options: u8, writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        match *self {
+            Self::Bincode => Bincode::from_options(options)?.serialize_into(writer, data),
+            #[cfg(feature = "rmp-serde")]
+            Self::MessagePack => MessagePack::from_options(options)?.serialize_into(writer, data),
+            #[cfg(feature = "postcard")]
+            Self::Postcard => Postcard::from_options(options)?.serialize_into(writer, data),
+            #[cfg(feature = "bitcode")]
+            Self::Bitcode => Bitcode::from_options(options)?.serialize_into(writer, data),
+            #[cfg(feature = "ciborium")]
+            Self::Cbor => Cbor::from_options(options)?.serialize_into(writer, data),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.disabled().into()),
+        }
+    }
+
+    /// deserialize data from the bytes, using the [Codec] of this id with the options
+    pub(crate) fn deserialize<T>(&self, options: u8, bytes: &[u8], limits: &Limits) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        match *self {
+            Self::Bincode => Bincode::from_options(options).at(Stage::Header)?.deserialize(bytes, limits),
+            #[cfg(feature = "rmp-serde")]
+            Self::MessagePack => MessagePack::from_options(options).at(Stage::Header)?.deserialize(bytes, limits),
+            #[cfg(feature = "postcard")]
+            Self::Postcard => Postcard::from_options(options).at(Stage::Header)?.deserialize(bytes, limits),
+            #[cfg(feature = "bitcode")]
+            Self::Bitcode => Bitcode::from_options(options).at(Stage::Header)?.deserialize(bytes, limits),
+            #[cfg(feature = "ciborium")]
+            Self::Cbor => Cbor::from_options(options).at(Stage::Header)?.deserialize(bytes, limits),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.disabled()).at(Stage::Header),
+        }.at(Stage::InnerDecode)
+    }
+
+    #[inline]
+    fn disabled(&self) -> error::ErrorKind {
+        error::ErrorKind::FeatureDisabled(self.name())
+    }
+}
+
+/// integer encoding of [Bincode], see [bincode::Options]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum IntEncoding {
+    /// integers and lengths are encoded with fixed size (for example, 8 bytes for lengths)
+    #[default]
+    Fixint,
+    /// integers and lengths are encoded with variable size, small numbers take 1 byte
+    Varint,
+}
+
+/// byte order of [Bincode], see [bincode::Options]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Endian {
+    /// little endian, the default
+    #[default]
+    Little,
+    /// big endian
+    Big,
+}
+
+/// [bincode](https://docs.rs/bincode/1), the default codec.
+///
+/// the default options are the same as [bincode::serialize]. [IntEncoding::Varint] makes
+/// payloads with small numbers and short strings much smaller.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Bincode {
+    pub(crate) int_encoding: IntEncoding,
+    pub(crate) endian: Endian,
+}
+
+impl Bincode {
+    const VARINT: u8 = 0b01;
+    const BIG_ENDIAN: u8 = 0b10;
+
+    /// create the default bincode codec
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// set the [IntEncoding], default is [IntEncoding::Fixint]
+    #[inline]
+    pub fn int_encoding(mut self, int_encoding: IntEncoding) -> Self {
+        self.int_encoding = int_encoding;
+        self
+    }
+
+    /// set the [Endian], default is [Endian::Little]
+    #[inline]
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+}
+
+impl Bincode {
+    fn deserialize_with<'de, O, T>(options: O, bytes: &'de [u8], limits: &Limits) -> error::Result<T>
+        where
+            O: Options,
+            T: Deserialize<'de>,
+    {
+        // Options::deserialize_seed drops the size limit for slices, so build the deserializer here
+        let result = limits.deserialize(&mut bincode::Deserializer::from_slice(bytes, options));
+
+        match (result, limits.allocation) {
+            (Err(err), Some(max)) if matches!(err.kind(), error::ErrorKind::BincodeError(err) if matches!(**err, bincode::ErrorKind::SizeLimit)) =>
+                Err(error::ErrorKind::LimitExceeded(crate::Limit::Allocation(max)).into()),
+            (result, _) => result,
+        }
+    }
+}
+
+impl Codec for Bincode {
+    const ID: CodecId = CodecId::Bincode;
+
+    #[inline]
+    fn options(&self) -> u8 {
+        let mut options = 0;
+        if self.int_encoding == IntEncoding::Varint {
+            options |= Self::VARINT;
+        }
+        if self.endian == Endian::Big {
+            options |= Self::BIG_ENDIAN;
+        }
+
+        options
+    }
+
+    #[inline]
+    fn from_options(options: u8) -> error::Result<Self> {
+        if options & !(Self::VARINT | Self::BIG_ENDIAN) != 0 {
+            return Err(error::ErrorKind::CustomError(format!("unknown options {} of codec bincode", options)).into());
+        }
+
+        Ok(Self {
+            int_encoding: if options & Self::VARINT == 0 { IntEncoding::Fixint } else { IntEncoding::Varint },
+            endian: if options & Self::BIG_ENDIAN == 0 { Endian::Little } else { Endian::Big },
+        })
+    }
+
+    fn serialize_into<W, T>(&self, writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        let options = bincode::DefaultOptions::new().allow_trailing_bytes();
+
+        match (self.int_encoding, self.endian) {
+            (IntEncoding::Fixint, Endian::Little) => options.with_fixint_encoding().with_little_endian().serialize_into(writer, data),
+            (IntEncoding::Fixint, Endian::Big) => options.with_fixint_encoding().with_big_endian().serialize_into(writer, data),
+            (IntEncoding::Varint, Endian::Little) => options.with_varint_encoding().with_little_endian().serialize_into(writer, data),
+            (IntEncoding::Varint, Endian::Big) => options.with_varint_encoding().with_big_endian().serialize_into(writer, data),
+        }?;
+
+        Ok(())
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8], limits: &Limits) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        let options = bincode::DefaultOptions::new()
+            .allow_trailing_bytes()
+            .with_limit(limits.allocation.unwrap_or(u64::MAX));
+
+        match (self.int_encoding, self.endian) {
+            (IntEncoding::Fixint, Endian::Little) => Self::deserialize_with(options.with_fixint_encoding().with_little_endian(), bytes, limits),
+            (IntEncoding::Fixint, Endian::Big) => Self::deserialize_with(options.with_fixint_encoding().with_big_endian(), bytes, limits),
+            (IntEncoding::Varint, Endian::Little) => Self::deserialize_with(options.with_varint_encoding().with_little_endian(), bytes, limits),
+            (IntEncoding::Varint, Endian::Big) => Self::deserialize_with(options.with_varint_encoding().with_big_endian(), bytes, limits),
+        }
+    }
+}
+
+#[cfg(feature = "rmp-serde")]
+/// [MessagePack](https://docs.rs/rmp-serde), structs are encoded as maps, so it is self-describing
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "rmp-serde")]
+impl Codec for MessagePack {
+    const ID: CodecId = CodecId::MessagePack;
+
+    fn serialize_into<W, T>(&self, mut writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        Ok(rmp_serde::encode::write_named(&mut writer, data)?)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8], limits: &Limits) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        // rmp-serde has no allocation limit, so Limits::allocation is not supported
+        limits.deserialize(&mut rmp_serde::Deserializer::from_read_ref(bytes))
+    }
+}
+
+#[cfg(feature = "postcard")]
+/// [postcard](https://docs.rs/postcard), integers and lengths are encoded as varint
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    const ID: CodecId = CodecId::Postcard;
+
+    fn serialize_into<W, T>(&self, writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        postcard::to_io(data, writer)?;
+        Ok(())
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8], limits: &Limits) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        // postcard has no allocation limit, so Limits::allocation is not supported
+        limits.deserialize(&mut postcard::Deserializer::from_bytes(bytes))
+    }
+}
+
+#[cfg(feature = "bitcode")]
+/// [bitcode](https://docs.rs/bitcode), the data is serialized in memory before it is written
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bitcode;
+
+#[cfg(feature = "bitcode")]
+impl Codec for Bitcode {
+    const ID: CodecId = CodecId::Bitcode;
+
+    fn serialize_into<W, T>(&self, mut writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        let bytes = bitcode::serialize(data)?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8], _limits: &Limits) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        // bitcode does not expose its deserializer, so neither Limits::depth nor Limits::allocation is supported
+        Ok(bitcode::deserialize(bytes)?)
+    }
+}
+
+#[cfg(feature = "ciborium")]
+/// [CBOR](https://docs.rs/ciborium), self-describing
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "ciborium")]
+impl Cbor {
+    const RECURSION_LIMIT: usize = 256;
+}
+
+#[cfg(feature = "ciborium")]
+impl Codec for Cbor {
+    const ID: CodecId = CodecId::Cbor;
+
+    fn serialize_into<W, T>(&self, writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        Ok(ciborium::into_writer(data, writer)?)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8], limits: &Limits) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        // ciborium does not expose its deserializer, use its own recursion limit (256 by default).
+        // it has no allocation limit, so Limits::allocation is not supported
+        let depth = limits.depth.unwrap_or(Self::RECURSION_LIMIT);
+
+        match ciborium::de::from_reader_with_recursion_limit(bytes, depth) {
+            Err(ciborium::de::Error::RecursionLimitExceeded) if limits.depth.is_some() =>
+                Err(error::ErrorKind::LimitExceeded(crate::Limit::Depth(depth)).into()),
+            result => Ok(result?),
+        }
+    }
+}
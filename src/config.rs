@@ -0,0 +1,89 @@
+use crate::{Bincode, Clock, Codec, CodecId, Limits, SharedClock, Verify};
+
+/// [Config] controls how data is converted into bytes, and the limits when converting back.
+///
+/// the default config is what [crate::to_vec], [crate::to_json] (and other functions) use.
+/// the decoder reads everything it needs from the payload, so [crate::from_slice] works for
+/// the bytes produced by any config.
+///
+/// ```rust
+/// use serde_tran::{CodecId, Config};
+///
+/// let config = Config::new().codec(CodecId::Bincode);
+/// let bytes = config.to_vec(&(1u8, "hello")).unwrap();
+///
+/// let value: (u8, String) = serde_tran::from_slice(&bytes).unwrap();
+/// assert_eq!(value, (1, "hello".to_string()));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub(crate) codec: CodecId,
+    pub(crate) bincode: Bincode,
+    pub(crate) verify: Verify,
+    pub(crate) limits: Limits,
+    pub(crate) clock: SharedClock,
+    pub(crate) nonce: bool,
+    pub(crate) replay: Option<crate::Replay>,
+    #[cfg(feature = "flate2")]
+    pub(crate) compression_level: Option<u32>,
+    #[cfg(feature = "token")]
+    pub(crate) secret: Option<crate::Secret>,
+}
+
+impl Config {
+    /// create the default config
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// set the inner [crate::Codec], default is [CodecId::Bincode]
+    #[inline]
+    pub fn codec(mut self, codec: CodecId) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// set the options of [Bincode], which are used when the codec is [CodecId::Bincode].
+    ///
+    /// the options are recorded in the payload, so the decoder does not need them.
+    #[inline]
+    pub fn bincode(mut self, bincode: Bincode) -> Self {
+        self.bincode = bincode;
+        self
+    }
+
+    /// set the [Limits] used when decoding, default is no limit.
+    ///
+    /// set them when the payload comes from untrusted sources (for example, a public endpoint).
+    #[inline]
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[cfg(feature = "flate2")]
+    /// set the gzip compression level, from 0 (no compression) to 9 (best compression),
+    /// default is 6, see [flate2::Compression]
+    #[inline]
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.compression_level = Some(level);
+        self
+    }
+
+    /// set the [Clock], default is [crate::SystemClock]
+    #[inline]
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    /// options of the selected codec, which are recorded in the payload
+    #[inline]
+    pub(crate) fn codec_options(&self) -> u8 {
+        match self.codec {
+            CodecId::Bincode => self.bincode.options(),
+            _ => 0,
+        }
+    }
+}
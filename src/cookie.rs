@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use crate::{Base, CodecId, Config, IntEncoding, error};
+use crate::error::{ResultExt, Stage};
+
+impl Config {
+    /// the configs which (usually) produce smaller payloads than this one, tried in order
+    fn smaller_configs(&self) -> Vec<Config> {
+        let mut configs = Vec::new();
+
+        #[cfg(feature = "flate2")]
+        configs.push(self.clone().compression_level(9));
+
+        if self.codec == CodecId::Bincode && self.bincode.int_encoding == IntEncoding::Fixint {
+            let varint = self.clone().bincode(self.bincode.int_encoding(IntEncoding::Varint));
+
+            #[cfg(feature = "flate2")]
+            configs.push(varint.clone().compression_level(9));
+            configs.push(varint);
+        }
+
+        configs
+    }
+
+    /// convert data to a base64 string no longer than budget, try the [Config::smaller_configs] if
+    /// the string of this config is too long
+    fn to_base64_within<T>(&self, data: &T, budget: usize) -> error::Result<String>
+        where T: Serialize + ?Sized
+    {
+        let mut smallest = self.to_base64(data)?;
+        if smallest.len() <= budget {
+            return Ok(smallest);
+        }
+
+        for config in self.smaller_configs() {
+            let value = config.to_base64(data)?;
+            if value.len() <= budget {
+                return Ok(value);
+            }
+            if value.len() < smallest.len() {
+                smallest = value;
+            }
+        }
+
+        Err(error::ErrorKind::TooLarge { size: smallest.len(), budget }).at(Stage::Encode)
+    }
+
+    /// convert data to a cookie value no longer than budget (in bytes), see [to_cookie_value]
+    pub fn to_cookie_value<T>(&self, data: &T, budget: usize) -> error::Result<String>
+        where T: Serialize + ?Sized
+    {
+        self.to_base64_within(data, budget)
+    }
+
+    /// convert data to a query parameter no longer than budget (in bytes), see [to_query_param]
+    pub fn to_query_param<T>(&self, data: &T, budget: usize) -> error::Result<String>
+        where T: Serialize + ?Sized
+    {
+        self.to_base64_within(data, budget)
+    }
+
+    /// convert data to at most max_cookies cookies named `{name}.0`, `{name}.1`, ..., and each
+    /// value is no longer than budget (in bytes). see [to_cookies]
+    pub fn to_cookies<T>(&self, name: &str, data: &T, budget: usize, max_cookies: usize) -> error::Result<Vec<(String, String)>>
+        where T: Serialize + ?Sized
+    {
+        if budget == 0 {
+            return Err(error::ErrorKind::CustomError("the budget of cookies should not be 0".to_string())).at(Stage::Encode);
+        }
+
+        let value = self.to_base64_within(data, budget.saturating_mul(max_cookies))?;
+
+        // base64 is ascii, so the value can be split at any byte
+        let cookies = value.as_bytes()
+            .chunks(budget)
+            .enumerate()
+            .map(|(i, chunk)| (format!("{}.{}", name, i), String::from_utf8_lossy(chunk).into_owned()))
+            .collect();
+
+        Ok(cookies)
+    }
+
+    /// join the cookies written by [Config::to_cookies] and convert them back to T, see [from_cookies]
+    pub fn from_cookies<I, K, V, T>(&self, name: &str, cookies: I) -> error::Result<T>
+        where
+            I: IntoIterator<Item = (K, V)>,
+            K: AsRef<str>,
+            V: AsRef<str>,
+            T: for<'de> Deserialize<'de>,
+    {
+        let value = join_cookies(name, cookies)?;
+        self.from_base(&value, Base::Base64)
+    }
+}
+
+/// convert data to a cookie value (a base64 string, which is safe in cookies).
+///
+/// if the value is longer than budget (in bytes), better compression (feature `flate2`) and
+/// smaller bincode options are tried, and [error::ErrorKind::TooLarge] is returned if the value
+/// still does not fit. browsers limit a cookie (including its name and attributes) to about 4096 bytes.
+///
+/// the value is decoded by [crate::from_base64].
+///
+/// ```rust
+/// use serde_tran::ErrorKind;
+///
+/// let value = serde_tran::to_cookie_value(&vec![1u8; 16], 4000).unwrap();
+/// assert_eq!(serde_tran::from_base64::<Vec<u8>>(&value).unwrap(), vec![1u8; 16]);
+///
+/// let noise: Vec<u64> = (0..4096u64).map(|i| i.wrapping_mul(0x9e3779b97f4a7c15)).collect();
+/// let err = serde_tran::to_cookie_value(&noise, 4000).unwrap_err();
+/// assert!(matches!(err.kind(), ErrorKind::TooLarge { .. }));
+/// ```
+pub fn to_cookie_value<T>(data: &T, budget: usize) -> error::Result<String>
+    where T: Serialize + ?Sized
+{
+    Config::default().to_cookie_value(data, budget)
+}
+
+/// convert data to a query parameter (a base64 string, which does not need percent-encoding).
+/// see [to_cookie_value] for the budget
+pub fn to_query_param<T>(data: &T, budget: usize) -> error::Result<String>
+    where T: Serialize + ?Sized
+{
+    Config::default().to_query_param(data, budget)
+}
+
+/// convert data to at most max_cookies cookies named `{name}.0`, `{name}.1`, ..., for the data
+/// which does not fit in one cookie. each value is no longer than budget (in bytes), see
+/// [to_cookie_value] for the budget.
+///
+/// the cookies are converted back by [from_cookies].
+///
+/// ```rust
+/// let data = (0..512u32).collect::<Vec<_>>();
+/// let cookies = serde_tran::to_cookies("session", &data, 1024, 4).unwrap();
+/// assert_eq!(cookies[0].0, "session.0");
+///
+/// let restored: Vec<u32> = serde_tran::from_cookies("session", cookies).unwrap();
+/// assert_eq!(restored, data);
+/// ```
+pub fn to_cookies<T>(name: &str, data: &T, budget: usize, max_cookies: usize) -> error::Result<Vec<(String, String)>>
+    where T: Serialize + ?Sized
+{
+    Config::default().to_cookies(name, data, budget, max_cookies)
+}
+
+/// join the cookies written by [to_cookies] and convert them back to T.
+///
+/// cookies is all the cookies of the request (in any order), the ones which are not named
+/// `{name}.{index}` are ignored.
+pub fn from_cookies<I, K, V, T>(name: &str, cookies: I) -> error::Result<T>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+        T: for<'de> Deserialize<'de>,
+{
+    Config::default().from_cookies(name, cookies)
+}
+
+/// join the values of the cookies named `{name}.0`, `{name}.1`, ... in order
+pub fn join_cookies<I, K, V>(name: &str, cookies: I) -> error::Result<String>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+{
+    let mut parts: Vec<(usize, V)> = cookies.into_iter()
+        .filter_map(|(key, value)| {
+            let index = key.as_ref().strip_prefix(name)?.strip_prefix('.')?.parse().ok()?;
+            Some((index, value))
+        })
+        .collect();
+    parts.sort_by_key(|(index, _)| *index);
+
+    if parts.is_empty() {
+        return Err(error::ErrorKind::CustomError(format!("cookie {}.0 is not found", name))).at(Stage::EnvelopeParse);
+    }
+    for (expected, (index, _)) in parts.iter().enumerate() {
+        if *index != expected {
+            return Err(error::ErrorKind::CustomError(format!("cookie {}.{} is not found", name, expected))).at(Stage::EnvelopeParse);
+        }
+    }
+
+    Ok(parts.iter().map(|(_, value)| value.as_ref()).collect())
+}
@@ -1,10 +1,24 @@
 use std::hash::Hasher;
-#[cfg(feature = "flate2")]
 use std::io::{Read, Write};
 #[cfg(feature = "flate2")]
 use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use serde::{Deserialize, Serialize};
-use crate::error;
+use crate::{Bincode, Codec, CodecId, Config, Fingerprint, Limit, Limits, Nonce, error};
+use crate::error::{ResultExt, Stage};
+
+/// the payload starts with [MAGIC] and [VERSION], followed by the codec byte (codec id in the lower
+/// 3 bits, [EXTENDED] flag in bit 3, codec options in the upper 4 bits), the [Extensions] (if
+/// flagged), the serialized data, and the hash (8 bytes, little endian) of all the bytes after [VERSION].
+///
+/// the payloads of the default codec ([CodecId::Bincode] with the default options) without
+/// extensions are still written in the layout of 1.x, the bincode serialized [DataHash], which
+/// does not start with [MAGIC], so the peers of 1.x can decode them. Both layouts are accepted
+/// by [from_slice].
+const MAGIC: [u8; 4] = *b"TRAN";
+const VERSION: u8 = 1;
+const HASH_SIZE: usize = std::mem::size_of::<u64>();
+const EXTENDED: u8 = 0b1000;
+const CODEC_ID: u8 = 0b0111;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct DataHash {
@@ -18,93 +32,423 @@ impl DataHash {
         std::hash::DefaultHasher::new()
     }
 
-    pub fn new(data: Vec<u8>) -> Self {
-        let hash = if cfg!(feature = "no-hash-validate") {
-            0u64
-        } else {
-            let mut hasher = Self::hasher();
-            hasher.write(&data);
-            hasher.finish()
-        };
-
-        Self {
-            data,
-            hash,
-        }
+    pub fn validate(&self) -> error::Result<()> {
+        Self::validate_checksum(&self.data, self.hash)
     }
 
-    pub fn validate(&self) -> error::Result<()> {
+    fn validate_checksum(data: &[u8], hash: u64) -> error::Result<()> {
         #[cfg(feature = "no-hash-validate")]
-        { return Ok(()) }
+        { Ok(()) }
 
         #[cfg(not(feature = "no-hash-validate"))]
         {
             let mut hasher = Self::hasher();
-            hasher.write(&self.data);
+            hasher.write(data);
 
             let expected = hasher.finish();
 
-            if self.hash == expected {
+            if hash == expected {
                 Ok(())
             } else {
-                Err(error::ErrorKind::HashError(expected, self.hash))
+                Err(error::ErrorKind::HashError(expected, hash)).at(Stage::Checksum)
             }
         }
     }
 }
 
-/// convert data to bytes
-pub fn to_vec<T>(data: &T) -> error::Result<Vec<u8>>
-    where T: Serialize + ?Sized,
-{
-    // serialize given data into bytes
-    let bytes = bincode::serialize(data)?;
+/// [HashWriter] forwards bytes to the inner writer, and feeds them into the [DataHash] hasher.
+struct HashWriter<W, H> {
+    inner: W,
+    hasher: H,
+}
 
-    // then, use DataHash to store bytes, calculate hash, and finally convert into bytes.
-    let data_hash = DataHash::new(bytes);
+/// [Extensions] are the optional parts of the payload header.
+///
+/// they start with a flags byte, followed by the flagged ones in order: the fingerprint (see
+/// [crate::Fingerprint]), the schema version (4 bytes, little endian, see [crate::Versioned]),
+/// the tag (1 byte length and the utf-8 string, see [crate::Tagged]) and the nonce (see [crate::Nonce]).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Extensions<'a> {
+    pub(crate) fingerprint: Option<Fingerprint>,
+    pub(crate) schema: Option<u32>,
+    pub(crate) tag: Option<&'a str>,
+    pub(crate) nonce: Option<Nonce>,
+}
+
+impl<'a> Extensions<'a> {
+    const FINGERPRINT: u8 = 0b0001;
+    const SCHEMA: u8 = 0b0010;
+    const TAG: u8 = 0b0100;
+    const NONCE: u8 = 0b1000;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.fingerprint.is_none() && self.schema.is_none() && self.tag.is_none() && self.nonce.is_none()
+    }
+
+    fn write_to<W: Write>(&self, mut writer: W) -> error::Result<()> {
+        let mut flags = 0;
+        if self.fingerprint.is_some() {
+            flags |= Self::FINGERPRINT;
+        }
+        if self.schema.is_some() {
+            flags |= Self::SCHEMA;
+        }
+        if self.tag.is_some() {
+            flags |= Self::TAG;
+        }
+        if self.nonce.is_some() {
+            flags |= Self::NONCE;
+        }
+        writer.write_all(&[flags])?;
+
+        if let Some(fingerprint) = &self.fingerprint {
+            fingerprint.write_to(&mut writer)?;
+        }
+        if let Some(schema) = self.schema {
+            writer.write_all(&schema.to_le_bytes())?;
+        }
+        if let Some(tag) = self.tag {
+            let length = u8::try_from(tag.len())
+                .map_err(|_| error::ErrorKind::CustomError(format!("tag {} is longer than 255 bytes", tag)))?;
+            writer.write_all(&[length])?;
+            writer.write_all(tag.as_bytes())?;
+        }
+        if let Some(nonce) = &self.nonce {
+            nonce.write_to(&mut writer)?;
+        }
 
-    #[allow(unused_mut)]
-    let mut data = bincode::serialize(&data_hash)?;
+        Ok(())
+    }
 
-    #[cfg(feature = "flate2")]
+    /// read the extensions written by [Self::write_to], return them and the rest bytes
+    fn read_from(bytes: &'a [u8]) -> error::Result<(Self, &'a [u8])> {
+        let truncated = || error::ErrorKind::CustomError("payload is truncated".to_string());
+
+        let (flags, mut bytes) = bytes.split_first().ok_or_else(truncated)?;
+        if flags & !(Self::FINGERPRINT | Self::SCHEMA | Self::TAG | Self::NONCE) != 0 {
+            return Err(error::ErrorKind::CustomError(format!("unknown extensions {}", flags)).into());
+        }
+
+        let mut extensions = Self::default();
+        if flags & Self::FINGERPRINT != 0 {
+            let (fingerprint, rest) = Fingerprint::read_from(bytes)?;
+            extensions.fingerprint = Some(fingerprint);
+            bytes = rest;
+        }
+        if flags & Self::SCHEMA != 0 {
+            let (schema, rest) = bytes.split_first_chunk::<4>().ok_or_else(truncated)?;
+            extensions.schema = Some(u32::from_le_bytes(*schema));
+            bytes = rest;
+        }
+        if flags & Self::TAG != 0 {
+            let (length, rest) = bytes.split_first().ok_or_else(truncated)?;
+            if rest.len() < *length as usize {
+                return Err(truncated().into());
+            }
+            let (tag, rest) = rest.split_at(*length as usize);
+            extensions.tag = Some(std::str::from_utf8(tag)
+                .map_err(|_| error::ErrorKind::CustomError("tag is not utf-8".to_string()))?);
+            bytes = rest;
+        }
+        if flags & Self::NONCE != 0 {
+            let (nonce, rest) = Nonce::read_from(bytes)?;
+            extensions.nonce = Some(nonce);
+            bytes = rest;
+        }
+
+        Ok((extensions, bytes))
+    }
+}
+
+/// [Content] is the parsed payload, whose hash is validated
+pub(crate) struct Content<'a> {
+    pub(crate) codec: CodecId,
+    pub(crate) options: u8,
+    pub(crate) extensions: Extensions<'a>,
+    pub(crate) data: &'a [u8],
+}
+
+impl Content<'_> {
+    /// check the fingerprint (if any), and deserialize the data into T
+    pub(crate) fn decode<T>(&self, limits: &Limits) -> error::Result<T>
+        where T: for <'de> Deserialize<'de>
     {
-        // gzip
-        let mut e = GzEncoder::new(Vec::new(), Compression::default());
-        e.write_all(&data)?;
-        data = e.finish()?;
+        if let Some(fingerprint) = &self.extensions.fingerprint {
+            fingerprint.check::<T>().at(Stage::Header)?;
+        }
+
+        self.codec.deserialize(self.options, self.data, limits)
+    }
+}
+
+impl<W: Write, H: Hasher> Write for HashWriter<W, H> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.write(&buf[..n]);
+        Ok(n)
     }
 
-    Ok(data)
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Config {
+    /// convert data to bytes, see [to_vec]
+    pub fn to_vec<T>(&self, data: &T) -> error::Result<Vec<u8>>
+        where T: Serialize + ?Sized,
+    {
+        let mut bytes = Vec::new();
+        self.to_writer(&mut bytes, data)?;
+
+        Ok(bytes)
+    }
+
+    /// write data into the writer, see [to_writer]
+    pub fn to_writer<W, T>(&self, writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        self.write_payload(writer, data, &Extensions::default())
+    }
+
+    /// write the payload of data into the writer, with the extensions
+    pub(crate) fn write_payload<W, T>(&self, writer: W, data: &T, extensions: &Extensions<'_>) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        #[cfg(feature = "flate2")]
+        let mut writer = GzEncoder::new(writer, self.compression_level.map(Compression::new).unwrap_or_default());
+        #[cfg(not(feature = "flate2"))]
+        let mut writer = writer;
+
+        let options = self.codec_options();
+        let nonce_extensions;
+        // a given nonce is kept, for example when the payload is written again to verify it
+        let new_nonce = if extensions.nonce.is_some() { None } else { self.new_nonce() };
+        let extensions = match new_nonce {
+            Some(nonce) => {
+                nonce_extensions = Extensions { nonce: Some(nonce), ..extensions.clone() };
+                &nonce_extensions
+            },
+            None => extensions,
+        };
+
+        if self.codec == CodecId::Bincode && options == Bincode::default().options() && extensions.is_empty() {
+            Self::write_legacy(&mut writer, data)?;
+        } else {
+            writer.write_all(&MAGIC).at(Stage::Encode)?;
+            writer.write_all(&[VERSION]).at(Stage::Encode)?;
+
+            // codec byte and data are hashed while they are written
+            let mut hash_writer = HashWriter {
+                inner: &mut writer,
+                hasher: DataHash::hasher(),
+            };
+            let flag = if extensions.is_empty() { 0 } else { EXTENDED };
+            hash_writer.write_all(&[self.codec.id() | flag | options << 4]).at(Stage::Encode)?;
+            if !extensions.is_empty() {
+                extensions.write_to(&mut hash_writer).at(Stage::Encode)?;
+            }
+            self.codec.serialize_into(options, &mut hash_writer, data).at(Stage::Encode)?;
+
+            let hash = if cfg!(feature = "no-hash-validate") {
+                0u64
+            } else {
+                hash_writer.hasher.finish()
+            };
+            writer.write_all(&hash.to_le_bytes()).at(Stage::Encode)?;
+        }
+
+        #[cfg(feature = "flate2")]
+        writer.finish().at(Stage::Encode)?;
+
+        Ok(())
+    }
+
+    /// write the payload in the layout of 1.x, the bincode serialized [DataHash] (the length and
+    /// the data, then the hash of the data), without holding the serialized data in memory
+    fn write_legacy<W, T>(mut writer: W, data: &T) -> error::Result<()>
+        where
+            W: Write,
+            T: Serialize + ?Sized,
+    {
+        let length = bincode::serialized_size(data).at(Stage::Encode)?;
+        writer.write_all(&length.to_le_bytes()).at(Stage::Encode)?;
+
+        let mut hash_writer = HashWriter {
+            inner: &mut writer,
+            hasher: DataHash::hasher(),
+        };
+        bincode::serialize_into(&mut hash_writer, data).at(Stage::Encode)?;
+
+        let hash = if cfg!(feature = "no-hash-validate") {
+            0u64
+        } else {
+            hash_writer.hasher.finish()
+        };
+        writer.write_all(&hash.to_le_bytes()).at(Stage::Encode)?;
+
+        Ok(())
+    }
+}
+
+/// convert data to bytes
+pub fn to_vec<T>(data: &T) -> error::Result<Vec<u8>>
+    where T: Serialize + ?Sized,
+{
+    Config::default().to_vec(data)
+}
+
+/// write data into the writer, the written bytes are the same as [to_vec].
+///
+/// the data is serialized directly into the writer, so the bytes are never held in memory.
+pub fn to_writer<W, T>(writer: W, data: &T) -> error::Result<()>
+    where
+        W: Write,
+        T: Serialize + ?Sized,
+{
+    Config::default().to_writer(writer, data)
+}
+
+/// read all bytes from the reader, and convert them into T. see [from_slice]
+pub fn from_reader<R, T>(reader: R) -> error::Result<T>
+    where
+        R: Read,
+        T: for <'de> Deserialize<'de>
+{
+    Config::default().from_reader(reader)
 }
 
 /// convert bytes into T
 pub fn from_slice<T>(slice: &[u8]) -> error::Result<T>
     where T: for <'de> Deserialize<'de>
 {
-    #[cfg(feature = "flate2")]
+    Config::default().from_slice(slice)
+}
+
+impl Config {
+    /// read all bytes from the reader, and convert them into T, see [from_reader].
+    ///
+    /// [crate::Limits::decoded_length] applies to the bytes read.
+    pub fn from_reader<R, T>(&self, mut reader: R) -> error::Result<T>
+        where
+            R: Read,
+            T: for <'de> Deserialize<'de>
     {
-        // gzip
-        let mut d = GzDecoder::new(slice);
-        let mut data = Vec::new();
-        d.read_to_end(&mut data)?;
+        let mut bytes = Vec::new();
+        match self.limits.decoded_length {
+            Some(max) => reader.take(max.saturating_add(1)).read_to_end(&mut bytes)?,
+            None => reader.read_to_end(&mut bytes)?,
+        };
 
-        // deserialize from bytes to DataHash, and do validate
-        let data_hash: DataHash = bincode::deserialize(&data)?;
-        data_hash.validate()?;
+        self.from_slice(&bytes)
+    }
 
-        // convert back to given data
-        let data = bincode::deserialize(data_hash.data.as_slice())?;
-        return Ok(data);
+    /// convert bytes into T, see [from_slice].
+    ///
+    /// the codec is read from the bytes, this config only provides [crate::Limits].
+    pub fn from_slice<T>(&self, slice: &[u8]) -> error::Result<T>
+        where T: for <'de> Deserialize<'de>
+    {
+        self.decode_slice(slice, |content| {
+            // the migrations are unknown here, so the payload may be of another schema version
+            if let Some(version) = content.extensions.schema {
+                return Err(error::ErrorKind::VersionedPayload(version)).at(Stage::Header);
+            }
+            content.decode(&self.limits)
+        })
+    }
+
+    /// parse the bytes, and convert the [Content] into T with decode
+    pub(crate) fn decode_slice<T, F>(&self, slice: &[u8], decode: F) -> error::Result<T>
+        where F: FnOnce(&Content<'_>) -> error::Result<T>
+    {
+        self.limits.check_decoded_length(slice.len() as u64).at(Stage::BaseDecode)?;
+
+        #[cfg(feature = "flate2")]
+        {
+            // gzip
+            let mut d = GzDecoder::new(slice);
+            let mut data = Vec::new();
+            match self.limits.decompressed_size {
+                Some(max) => d.take(max.saturating_add(1)).read_to_end(&mut data).at(Stage::Decompress)?,
+                None => d.read_to_end(&mut data).at(Stage::Decompress)?,
+            };
+
+            self.from_payload(&data, decode)
+        }
+
+        #[cfg(not(feature = "flate2"))]
+        {
+            self.from_payload(slice, decode)
+        }
     }
 
-    #[cfg(not(feature = "flate2"))]
+    /// parse the (decompressed) payload, and convert the [Content] into T with decode
+    #[allow(clippy::wrong_self_convention)]
+    fn from_payload<T, F>(&self, payload: &[u8], decode: F) -> error::Result<T>
+        where F: FnOnce(&Content<'_>) -> error::Result<T>
     {
-        // deserialize from bytes to DataHash, and do validate
-        let data_hash: DataHash = bincode::deserialize(slice)?;
-        data_hash.validate()?;
+        if let Some(max) = self.limits.decompressed_size {
+            if payload.len() as u64 > max {
+                return Err(error::ErrorKind::LimitExceeded(Limit::DecompressedSize(max))).at(Stage::Decompress);
+            }
+        }
+
+        let Some(payload) = payload.strip_prefix(&MAGIC) else {
+            // deserialize from bytes to DataHash, and do validate. the depth limit is for the data,
+            // not for the DataHash wrapper
+            let limits = Limits { depth: None, ..self.limits };
+            let data_hash: DataHash = Bincode::default().deserialize(payload, &limits).at(Stage::Header)?;
+            data_hash.validate()?;
+            self.check_replay(None)?;
+
+            // convert back to given data
+            return decode(&Content {
+                codec: Bincode::ID,
+                options: Bincode::default().options(),
+                extensions: Extensions::default(),
+                data: &data_hash.data,
+            });
+        };
+
+        let (version, payload) = payload.split_first()
+            .ok_or_else(|| error::ErrorKind::CustomError("payload is truncated".to_string())).at(Stage::Header)?;
+        if *version != VERSION {
+            return Err(error::ErrorKind::CustomError(format!("unsupported payload version {}", version))).at(Stage::Header);
+        }
+
+        if payload.len() < 1 + HASH_SIZE {
+            return Err(error::ErrorKind::CustomError("payload is truncated".to_string())).at(Stage::Header);
+        }
+
+        // validate the hash of codec byte, extensions and data
+        let (content, hash) = payload.split_at(payload.len() - HASH_SIZE);
+        let mut hash_bytes = [0u8; HASH_SIZE];
+        hash_bytes.copy_from_slice(hash);
+        DataHash::validate_checksum(content, u64::from_le_bytes(hash_bytes))?;
 
         // convert back to given data
-        let data = bincode::deserialize(data_hash.data.as_slice())?;
-        return Ok(data);
+        let (codec, data) = content.split_first()
+            .ok_or_else(|| error::ErrorKind::CustomError("payload is truncated".to_string())).at(Stage::Header)?;
+        let (extensions, data) = if codec & EXTENDED != 0 {
+            Extensions::read_from(data).at(Stage::Header)?
+        } else {
+            (Extensions::default(), data)
+        };
+        let codec_id = CodecId::from_id(codec & CODEC_ID).at(Stage::Header)?;
+        let nonce = extensions.nonce;
+        self.check_replay(nonce.as_ref())?;
+
+        decode(&Content {
+            codec: codec_id,
+            options: codec >> 4,
+            extensions,
+            data,
+        }).inspect_err(|_| self.forget_nonce(nonce.as_ref()))
     }
 }
@@ -1,7 +1,203 @@
 use std::fmt::{Display, Formatter};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
 
-pub type Result<T> = core::result::Result<T, ErrorKind>;
+pub type Result<T> = core::result::Result<T, Error>;
 
+/// [Stage] is the step of the encoding or decoding pipeline where an [Error] happened.
+///
+/// decoding goes through [Stage::EnvelopeParse] (for [crate::Json]), [Stage::BaseDecode],
+/// [Stage::Decompress], [Stage::Header], [Stage::Checksum], [Stage::InnerDecode] and
+/// [Stage::Validate] (for tokens and nonces) in order.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Stage {
+    /// serializing data, compressing, base encoding or writing the envelope
+    Encode,
+    /// parsing the json envelope, see [crate::Json]
+    EnvelopeParse,
+    /// decoding the base58 or base64 string
+    BaseDecode,
+    /// decompressing the payload (feature `flate2`)
+    Decompress,
+    /// parsing the payload header: magic, version, codec id and codec options
+    Header,
+    /// validating the hash of the payload
+    Checksum,
+    /// deserializing the data with the codec
+    InnerDecode,
+    /// verifying the round trip, see [crate::Verify]
+    Verify,
+    /// validating the decoded claims, such as the expiry of a token
+    Validate,
+}
+
+impl Stage {
+    /// a stable, machine-readable name of the stage, such as `base_decode`
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Self::Encode => "encode",
+            Self::EnvelopeParse => "envelope_parse",
+            Self::BaseDecode => "base_decode",
+            Self::Decompress => "decompress",
+            Self::Header => "header",
+            Self::Checksum => "checksum",
+            Self::InnerDecode => "inner_decode",
+            Self::Verify => "verify",
+            Self::Validate => "validate",
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// [Error] is the error of all functions in this crate: the [ErrorKind] and the [Stage] where it happened.
+///
+/// ```rust
+/// use serde_tran::{ErrorKind, Stage};
+///
+/// let err = serde_tran::from_base64::<u32>("not base64!").unwrap_err();
+/// assert_eq!(err.stage(), Some(Stage::BaseDecode));
+/// assert!(matches!(err.kind(), ErrorKind::Base64DecodeError(_)));
+/// assert_eq!(err.code(), "base64_decode");
+/// ```
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    stage: Option<Stage>,
+    path: Option<crate::Path>,
+}
+
+impl Error {
+    /// what went wrong
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// take the [ErrorKind] out
+    #[inline]
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// where it went wrong, `None` when the error is not raised in a pipeline stage
+    /// (for example reading from the reader of [crate::from_reader] fails)
+    #[inline]
+    pub fn stage(&self) -> Option<Stage> {
+        self.stage
+    }
+
+    /// where in the data decoding failed, such as `orders[3].items[0].price`, see [crate::Path]
+    #[inline]
+    pub fn path(&self) -> Option<&crate::Path> {
+        self.path.as_ref()
+    }
+
+    /// a stable, machine-readable code of the [ErrorKind], see [ErrorKind::code]
+    #[inline]
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    /// the HTTP status code of the error responses of the web integrations: 500 when encoding fails,
+    /// 413 when a [crate::Limits] is exceeded, and 400 for other decoding errors
+    #[cfg(all(feature = "serde_json", any(feature = "actix-web", feature = "axum", feature = "tower")))]
+    pub(crate) fn http_status(&self) -> u16 {
+        match (self.stage, &self.kind) {
+            (None | Some(Stage::Encode | Stage::Verify), _) => 500,
+            (_, ErrorKind::LimitExceeded(_)) => 413,
+            _ => 400,
+        }
+    }
+
+    /// the JSON body of the errors which are not of this crate in the web integrations (such as
+    /// reading the request body), in the shape of the `Serialize` of [Error]
+    #[cfg(all(feature = "serde_json", any(feature = "actix-web", feature = "axum", feature = "tower")))]
+    pub(crate) fn http_body_of(status: u16, message: String) -> String {
+        let code = if status == 413 { "limit_exceeded" } else { "io" };
+        serde_json::json!({ "code": code, "stage": null, "path": null, "message": message }).to_string()
+    }
+
+    /// record the stage, unless an inner stage is already recorded
+    #[inline]
+    pub(crate) fn at(mut self, stage: Stage) -> Self {
+        self.stage.get_or_insert(stage);
+        self
+    }
+
+    /// record the path, unless an inner path is already recorded
+    #[inline]
+    pub(crate) fn with_path(mut self, path: Option<crate::Path>) -> Self {
+        if self.path.is_none() {
+            self.path = path;
+        }
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(stage) = self.stage {
+            write!(f, "{}", stage)?;
+            if self.path.is_some() {
+                f.write_str(" ")?;
+            }
+        }
+        if let Some(path) = &self.path {
+            write!(f, "at `{}`", path)?;
+        }
+        if self.stage.is_some() || self.path.is_some() {
+            f.write_str(": ")?;
+        }
+
+        Display::fmt(&self.kind, f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+/// [Error] is serialized as `{"code": ..., "stage": ..., "path": ..., "message": ...}`, for the
+/// error responses of the web integrations. `stage` and `path` are `null` when they are not recorded.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 4)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("stage", &self.stage.map(|stage| stage.code()))?;
+        state.serialize_field("path", &self.path.as_ref().map(|path| path.to_string()))?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<ErrorKind> for Error {
+    #[inline]
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind, stage: None, path: None }
+    }
+}
+
+/// record the [Stage] of the error in a result, see [Error::at]
+pub(crate) trait ResultExt<T> {
+    fn at(self, stage: Stage) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for core::result::Result<T, E> {
+    #[inline]
+    fn at(self, stage: Stage) -> Result<T> {
+        self.map_err(|err| err.into().at(stage))
+    }
+}
+
+/// [ErrorKind] is what went wrong, see [Error::kind]
+#[non_exhaustive]
 #[derive(Debug)]
 pub enum ErrorKind {
     IOError(std::io::Error),
@@ -11,6 +207,43 @@ pub enum ErrorKind {
     HashError(u64, u64),
     /// Custom error
     CustomError(String),
+    /// The feature (named in it) is required but not enabled
+    FeatureDisabled(&'static str),
+    /// A limit in [crate::Limits] is exceeded when decoding
+    LimitExceeded(crate::Limit),
+    /// Round trip verification error, the message names the type and the likely cause
+    VerifyError(String),
+    /// The payload was written (by the `*_typed` functions) with the fingerprint of another type
+    TypeMismatch {
+        /// the type being decoded
+        expected: String,
+        /// the type which the payload was written with
+        found: String,
+    },
+    /// The schema version of the payload has no decoder in [crate::Versioned::migrations]. (type, version)
+    UnknownVersion(&'static str, u32),
+    /// The payload has a schema version (see [crate::Versioned]), but it is decoded without the
+    /// migrations, use [crate::from_slice_versioned]. (version)
+    VersionedPayload(u32),
+    /// The tag of the payload is not registered in the [crate::Registry]
+    UnknownTag(String),
+    /// The payload has no tag (see [crate::Tagged]), but it is decoded by a [crate::Registry]
+    MissingTag,
+    /// The MAC of the token does not match, the token is forged or signed with another secret
+    MacMismatch,
+    /// The token (or the nonce, see [crate::Config::replay_protection]) expired at the time
+    Expired(std::time::SystemTime),
+    /// The nonce of the payload is seen, see [crate::Config::replay_protection]
+    Replayed,
+    /// The payload has no nonce (see [crate::Config::nonce]), but [crate::Config::replay_protection] requires one
+    MissingNonce,
+    /// The encoded value is larger than the budget, even with the best compression
+    TooLarge {
+        /// the size of the smallest encoded value
+        size: usize,
+        /// the max size
+        budget: usize,
+    },
     #[cfg(feature = "bs58")]
     /// base58 decode error
     Base58DecodeError(bs58::decode::Error),
@@ -20,6 +253,24 @@ pub enum ErrorKind {
     #[cfg(feature = "serde_json")]
     /// serde_json decode and encode error
     SerdeJsonError(serde_json::Error),
+    #[cfg(feature = "rmp-serde")]
+    /// MessagePack encode error
+    MessagePackEncodeError(rmp_serde::encode::Error),
+    #[cfg(feature = "rmp-serde")]
+    /// MessagePack decode error
+    MessagePackDecodeError(rmp_serde::decode::Error),
+    #[cfg(feature = "postcard")]
+    /// postcard encode and decode error
+    PostcardError(postcard::Error),
+    #[cfg(feature = "bitcode")]
+    /// bitcode encode and decode error
+    BitcodeError(bitcode::Error),
+    #[cfg(feature = "ciborium")]
+    /// CBOR encode error
+    CborEncodeError(ciborium::ser::Error<std::io::Error>),
+    #[cfg(feature = "ciborium")]
+    /// CBOR decode error
+    CborDecodeError(ciborium::de::Error<std::io::Error>),
 }
 
 fn error_kind_feature_display_arm(kind: &ErrorKind, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -38,6 +289,36 @@ fn error_kind_feature_display_arm(kind: &ErrorKind, f: &mut Formatter<'_>) -> st
         return Display::fmt(err, f)
     }
 
+    #[cfg(feature = "rmp-serde")]
+    if let ErrorKind::MessagePackEncodeError(err) = kind {
+        return Display::fmt(err, f)
+    }
+
+    #[cfg(feature = "rmp-serde")]
+    if let ErrorKind::MessagePackDecodeError(err) = kind {
+        return Display::fmt(err, f)
+    }
+
+    #[cfg(feature = "postcard")]
+    if let ErrorKind::PostcardError(err) = kind {
+        return Display::fmt(err, f)
+    }
+
+    #[cfg(feature = "bitcode")]
+    if let ErrorKind::BitcodeError(err) = kind {
+        return Display::fmt(err, f)
+    }
+
+    #[cfg(feature = "ciborium")]
+    if let ErrorKind::CborEncodeError(err) = kind {
+        return Display::fmt(err, f)
+    }
+
+    #[cfg(feature = "ciborium")]
+    if let ErrorKind::CborDecodeError(err) = kind {
+        return Display::fmt(err, f)
+    }
+
     unreachable!()
 }
 
@@ -48,47 +329,139 @@ impl Display for ErrorKind {
             Self::BincodeError(err) => write!(f, "bincode::error: {}", err),
             Self::HashError(expected, got) => write!(f, "hash error: expected {} but got {}", expected, got),
             Self::CustomError(s) => Display::fmt(s, f),
+            Self::FeatureDisabled(feature) => write!(f, "feature {} is not enabled", feature),
+            Self::LimitExceeded(limit) => write!(f, "limit exceeded: {}", limit),
+            Self::VerifyError(s) => write!(f, "verify error: {}", s),
+            Self::TypeMismatch { expected, found } => write!(f, "type mismatch: expected {} but found {}", expected, found),
+            Self::UnknownVersion(name, version) => write!(f, "unknown schema version {} of {}", version, name),
+            Self::VersionedPayload(version) => write!(f, "the payload has schema version {}, decode it with the migrations", version),
+            Self::UnknownTag(tag) => write!(f, "unknown tag {}", tag),
+            Self::MissingTag => write!(f, "the payload has no tag"),
+            Self::MacMismatch => write!(f, "mac mismatch"),
+            Self::Replayed => write!(f, "the payload is replayed"),
+            Self::MissingNonce => write!(f, "the payload has no nonce"),
+            Self::TooLarge { size, budget } => write!(f, "too large: {} bytes is larger than the budget {} bytes", size, budget),
+            Self::Expired(at) => match at.duration_since(std::time::UNIX_EPOCH) {
+                Ok(since) => write!(f, "expired at {} (unix time)", since.as_secs()),
+                Err(_) => write!(f, "expired"),
+            },
 
             _ => error_kind_feature_display_arm(self, f)
         }
     }
 }
 
-impl std::error::Error for ErrorKind {}
-
-impl From<bincode::Error> for ErrorKind {
-    fn from(err: bincode::Error) -> Self {
-        Self::BincodeError(err)
+impl ErrorKind {
+    /// a stable, machine-readable code, such as `hash_mismatch` or `feature_disabled`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::IOError(_) => "io",
+            Self::BincodeError(_) => "bincode",
+            Self::HashError(..) => "hash_mismatch",
+            Self::CustomError(_) => "custom",
+            Self::FeatureDisabled(_) => "feature_disabled",
+            Self::LimitExceeded(_) => "limit_exceeded",
+            Self::VerifyError(_) => "verify",
+            Self::TypeMismatch { .. } => "type_mismatch",
+            Self::UnknownVersion(..) => "unknown_version",
+            Self::VersionedPayload(_) => "versioned_payload",
+            Self::UnknownTag(_) => "unknown_tag",
+            Self::MissingTag => "missing_tag",
+            Self::MacMismatch => "mac_mismatch",
+            Self::Expired(_) => "expired",
+            Self::Replayed => "replayed",
+            Self::MissingNonce => "missing_nonce",
+            Self::TooLarge { .. } => "too_large",
+            #[cfg(feature = "bs58")]
+            Self::Base58DecodeError(_) => "base58_decode",
+            #[cfg(feature = "base64")]
+            Self::Base64DecodeError(_) => "base64_decode",
+            #[cfg(feature = "serde_json")]
+            Self::SerdeJsonError(_) => "json",
+            #[cfg(feature = "rmp-serde")]
+            Self::MessagePackEncodeError(_) => "messagepack_encode",
+            #[cfg(feature = "rmp-serde")]
+            Self::MessagePackDecodeError(_) => "messagepack_decode",
+            #[cfg(feature = "postcard")]
+            Self::PostcardError(_) => "postcard",
+            #[cfg(feature = "bitcode")]
+            Self::BitcodeError(_) => "bitcode",
+            #[cfg(feature = "ciborium")]
+            Self::CborEncodeError(_) => "cbor_encode",
+            #[cfg(feature = "ciborium")]
+            Self::CborDecodeError(_) => "cbor_decode",
+        }
     }
 }
 
-impl From<std::io::Error> for ErrorKind {
-    #[inline]
-    fn from(err: std::io::Error) -> Self {
-        Self::IOError(err)
+impl std::error::Error for ErrorKind {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IOError(err) => Some(err),
+            Self::BincodeError(err) => Some(err),
+            #[cfg(feature = "bs58")]
+            Self::Base58DecodeError(err) => Some(err),
+            #[cfg(feature = "base64")]
+            Self::Base64DecodeError(err) => Some(err),
+            #[cfg(feature = "serde_json")]
+            Self::SerdeJsonError(err) => Some(err),
+            #[cfg(feature = "rmp-serde")]
+            Self::MessagePackEncodeError(err) => Some(err),
+            #[cfg(feature = "rmp-serde")]
+            Self::MessagePackDecodeError(err) => Some(err),
+            #[cfg(feature = "postcard")]
+            Self::PostcardError(err) => Some(err),
+            #[cfg(feature = "bitcode")]
+            Self::BitcodeError(err) => Some(err),
+            #[cfg(feature = "ciborium")]
+            Self::CborEncodeError(err) => Some(err),
+            #[cfg(feature = "ciborium")]
+            Self::CborDecodeError(err) => Some(err),
+            _ => None,
+        }
     }
 }
 
-#[cfg(feature = "bs58")]
-impl From<bs58::decode::Error> for ErrorKind {
-    #[inline]
-    fn from(err: bs58::decode::Error) -> Self {
-        Self::Base58DecodeError(err)
-    }
-}
+/// implement `From<$ty>` for both [ErrorKind] and [Error]
+macro_rules! impl_from {
+    ($($(#[$meta:meta])* $ty:ty => $variant:ident,)*) => {$(
+        $(#[$meta])*
+        impl From<$ty> for ErrorKind {
+            #[inline]
+            fn from(err: $ty) -> Self {
+                Self::$variant(err)
+            }
+        }
 
-#[cfg(feature = "base64")]
-impl From<base64::DecodeError> for ErrorKind {
-    #[inline]
-    fn from(err: base64::DecodeError) -> Self {
-        Self::Base64DecodeError(err)
-    }
+        $(#[$meta])*
+        impl From<$ty> for Error {
+            #[inline]
+            fn from(err: $ty) -> Self {
+                ErrorKind::$variant(err).into()
+            }
+        }
+    )*};
 }
 
-#[cfg(feature = "serde_json")]
-impl From<serde_json::Error> for ErrorKind {
-    #[inline]
-    fn from(err: serde_json::Error) -> Self {
-        Self::SerdeJsonError(err)
-    }
+impl_from! {
+    bincode::Error => BincodeError,
+    std::io::Error => IOError,
+    #[cfg(feature = "bs58")]
+    bs58::decode::Error => Base58DecodeError,
+    #[cfg(feature = "base64")]
+    base64::DecodeError => Base64DecodeError,
+    #[cfg(feature = "serde_json")]
+    serde_json::Error => SerdeJsonError,
+    #[cfg(feature = "rmp-serde")]
+    rmp_serde::encode::Error => MessagePackEncodeError,
+    #[cfg(feature = "rmp-serde")]
+    rmp_serde::decode::Error => MessagePackDecodeError,
+    #[cfg(feature = "postcard")]
+    postcard::Error => PostcardError,
+    #[cfg(feature = "bitcode")]
+    bitcode::Error => BitcodeError,
+    #[cfg(feature = "ciborium")]
+    ciborium::ser::Error<std::io::Error> => CborEncodeError,
+    #[cfg(feature = "ciborium")]
+    ciborium::de::Error<std::io::Error> => CborDecodeError,
 }
@@ -0,0 +1,77 @@
+use std::ops::{Deref, DerefMut};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::Json;
+
+/// [Tran] is a field wrapper: `T` is serialized as a [Json] envelope, and deserialized back from it,
+/// so one large field of a struct is compact while the others stay readable.
+///
+/// the envelope is encoded and decoded with the default [crate::Config]. the extractors of the
+/// web integrations (such as `serde_tran::axum::Tran`) are other types, in their own modules.
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_tran::Tran;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct MyResponse {
+///     data: Tran<Vec<u32>>,
+///     code: u16,
+/// }
+///
+/// let response = MyResponse { data: Tran((0..100).collect()), code: 200 };
+/// let text = serde_json::to_string(&response).unwrap();
+/// assert!(text.starts_with(r#"{"data":{"f":"base64","v":""#));
+///
+/// let restored: MyResponse = serde_json::from_str(&text).unwrap();
+/// assert_eq!(restored.data.len(), 100);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Tran<T>(pub T);
+
+impl<T> Tran<T> {
+    /// take the data out
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Tran<T> {
+    #[inline]
+    fn from(data: T) -> Self {
+        Self(data)
+    }
+}
+
+impl<T> Deref for Tran<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Tran<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> Serialize for Tran<T>
+    where T: Serialize
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Json::serialize_value(&self.0, serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Tran<T>
+    where T: for<'a> Deserialize<'a>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = Json::deserialize(deserializer)?;
+        json.to_value().map(Tran).map_err(serde::de::Error::custom)
+    }
+}
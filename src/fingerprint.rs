@@ -0,0 +1,403 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use crate::{Config, Extensions, error};
+
+/// [Fingerprint] identifies the shape of a type, it is embedded in the payloads written by
+/// the `*_typed` functions (such as [Config::to_vec_typed]).
+///
+/// when such a payload is decoded as another type, [crate::from_slice] (and everything built on
+/// it, such as [crate::Json::to_value]) returns [error::ErrorKind::TypeMismatch] instead of
+/// decoding garbage.
+///
+/// the fingerprint is derived from the `Deserialize` impl: the serde names of the structs and
+/// enums, the field and variant names, and the types of the fields. The module path is not
+/// included, so the same type defined in different crates has the same fingerprint. Only the
+/// first variant of an enum is looked into.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) struct Fingerprint {
+    pub(crate) hash: u64,
+    /// the rust type name, only used in error messages
+    pub(crate) name: String,
+}
+
+impl Fingerprint {
+    /// the names longer than this are truncated in the payload
+    const MAX_NAME_LENGTH: usize = u8::MAX as usize;
+
+    pub(crate) fn of<T>() -> Self
+        where T: for<'de> Deserialize<'de>
+    {
+        let shape = RefCell::new(String::new());
+
+        // the probe stops when the type refuses a value (such as NonZeroU32) or nests too deep,
+        // the shape recorded so far is still stable
+        let _ = T::deserialize(Probe { shape: &shape, depth: Probe::MAX_DEPTH });
+
+        Self {
+            hash: fnv1a(shape.into_inner().as_bytes()),
+            name: std::any::type_name::<T>().to_string(),
+        }
+    }
+
+    /// write the fingerprint: hash (8 bytes, little endian), name length (1 byte) and name
+    pub(crate) fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let name = truncate(&self.name, Self::MAX_NAME_LENGTH);
+
+        writer.write_all(&self.hash.to_le_bytes())?;
+        writer.write_all(&[name.len() as u8])?;
+        writer.write_all(name.as_bytes())
+    }
+
+    /// read the fingerprint written by [Self::write_to], return it and the rest bytes
+    pub(crate) fn read_from(bytes: &[u8]) -> error::Result<(Self, &[u8])> {
+        let truncated = || error::ErrorKind::CustomError("payload is truncated".to_string());
+
+        let (hash, bytes) = bytes.split_first_chunk::<8>().ok_or_else(truncated)?;
+        let (length, bytes) = bytes.split_first().ok_or_else(truncated)?;
+        if bytes.len() < *length as usize {
+            return Err(truncated().into());
+        }
+        let (name, bytes) = bytes.split_at(*length as usize);
+
+        let fingerprint = Self {
+            hash: u64::from_le_bytes(*hash),
+            name: String::from_utf8_lossy(name).into_owned(),
+        };
+        Ok((fingerprint, bytes))
+    }
+
+    /// check that the payload was written with the fingerprint of T
+    pub(crate) fn check<T>(&self) -> error::Result<()>
+        where T: for<'de> Deserialize<'de>
+    {
+        let expected = Self::of::<T>();
+        if expected.hash != self.hash {
+            return Err(error::ErrorKind::TypeMismatch {
+                expected: expected.name,
+                found: self.name.clone(),
+            }.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// return the fingerprint of T, which is embedded by the `*_typed` functions such as [to_vec_typed]
+pub fn fingerprint<T>() -> u64
+    where T: for<'de> Deserialize<'de>
+{
+    Fingerprint::of::<T>().hash
+}
+
+impl Config {
+    /// convert data to bytes like [Config::to_vec], with the fingerprint of T embedded.
+    /// see [to_vec_typed]
+    pub fn to_vec_typed<T>(&self, data: &T) -> error::Result<Vec<u8>>
+        where T: Serialize + for<'de> Deserialize<'de>
+    {
+        let mut bytes = Vec::new();
+        let extensions = Extensions { fingerprint: Some(Fingerprint::of::<T>()), ..Extensions::default() };
+        self.write_payload(&mut bytes, data, &extensions)?;
+
+        Ok(bytes)
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// convert given data into [crate::Json] like [Config::to_json], with the fingerprint of T embedded
+    pub fn to_json_typed<T>(&self, data: &T) -> error::Result<crate::Json>
+        where T: Serialize + for<'de> Deserialize<'de>
+    {
+        let bytes = self.to_vec_typed(data)?;
+        crate::Json::from_payload(crate::Json::DEFAULT_FORMAT, &bytes)
+    }
+}
+
+/// convert data to bytes, with the fingerprint of T embedded, so decoding the bytes as another
+/// type fails with [error::ErrorKind::TypeMismatch].
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_tran::ErrorKind;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct LoginRequest { username: String, password: String }
+/// #[derive(Serialize, Deserialize)]
+/// struct Comment { author: String, text: String }
+///
+/// let request = LoginRequest { username: "user".to_string(), password: "pass".to_string() };
+/// let bytes = serde_tran::to_vec_typed(&request).unwrap();
+///
+/// let err = serde_tran::from_slice::<Comment>(&bytes).err().unwrap();
+/// assert!(matches!(err.kind(), ErrorKind::TypeMismatch { .. }));
+/// ```
+pub fn to_vec_typed<T>(data: &T) -> error::Result<Vec<u8>>
+    where T: Serialize + for<'de> Deserialize<'de>
+{
+    Config::default().to_vec_typed(data)
+}
+
+#[cfg(feature = "serde_json")]
+/// convert given data into [crate::Json], with the fingerprint of T embedded. see [to_vec_typed]
+pub fn to_json_typed<T>(data: &T) -> error::Result<crate::Json>
+    where T: Serialize + for<'de> Deserialize<'de>
+{
+    Config::default().to_json_typed(data)
+}
+
+/// 64 bits FNV-1a, which (unlike [std::hash::DefaultHasher]) is stable across rust versions
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// the probe is stopped
+#[derive(Debug)]
+struct Stop;
+
+impl Display for Stop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("stop")
+    }
+}
+
+impl std::error::Error for Stop {}
+
+impl de::Error for Stop {
+    fn custom<T: Display>(_msg: T) -> Self {
+        Stop
+    }
+}
+
+/// [Probe] is a deserializer which provides the simplest value of each type,
+/// and records the shape of the requested type.
+#[derive(Clone, Copy)]
+struct Probe<'a> {
+    shape: &'a RefCell<String>,
+    depth: usize,
+}
+
+impl Probe<'_> {
+    /// recursive types are cut here
+    const MAX_DEPTH: usize = 16;
+
+    #[inline]
+    fn write(&self, s: &str) {
+        self.shape.borrow_mut().push_str(s);
+    }
+
+    #[inline]
+    fn nested(self) -> Result<Self, Stop> {
+        if self.depth == 0 {
+            return Err(Stop);
+        }
+
+        Ok(Self { depth: self.depth - 1, ..self })
+    }
+
+    fn elements<'de, V: Visitor<'de>>(self, len: usize, open: &str, close: &str, visitor: V) -> Result<V::Value, Stop> {
+        self.write(open);
+        let value = visitor.visit_seq(Elements { probe: self.nested()?, remaining: len, first: true })?;
+        self.write(close);
+        Ok(value)
+    }
+}
+
+macro_rules! probe_primitive {
+    ($($method:ident => $visit:ident($($value:expr)?)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Stop> {
+                self.write(stringify!($visit));
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Probe<'_> {
+    type Error = Stop;
+
+    probe_primitive! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0), deserialize_i16 => visit_i16(0), deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0), deserialize_i128 => visit_i128(0),
+        deserialize_u8 => visit_u8(0), deserialize_u16 => visit_u16(0), deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0), deserialize_u128 => visit_u128(0),
+        deserialize_f32 => visit_f32(0.0), deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char('\0'),
+        deserialize_str => visit_str(""), deserialize_string => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]), deserialize_byte_buf => visit_bytes(&[]),
+        deserialize_unit => visit_unit(),
+        // self-describing types (such as `#[serde(untagged)]`)
+        deserialize_any => visit_unit(),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Stop> {
+        self.write("?");
+        visitor.visit_some(self.nested()?)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Stop> {
+        self.write(name);
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Stop> {
+        self.write(name);
+        self.write("(");
+        let value = visitor.visit_newtype_struct(self.nested()?)?;
+        self.write(")");
+        Ok(value)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Stop> {
+        self.elements(1, "[", "]", visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Stop> {
+        self.elements(len, "(", ")", visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Stop> {
+        self.write(name);
+        self.elements(len, "(", ")", visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Stop> {
+        self.write("{");
+        let value = visitor.visit_map(Elements { probe: self.nested()?, remaining: 1, first: true })?;
+        self.write("}");
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Stop> {
+        self.write(name);
+        self.write("{");
+        self.write(&fields.join(","));
+        self.write("}");
+        // the fields are provided as a sequence, which every derived struct accepts
+        self.elements(fields.len(), "(", ")", visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Stop> {
+        self.write(name);
+        self.write("<");
+        self.write(&variants.join("|"));
+        self.write(">");
+        visitor.visit_enum(self.nested()?)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Stop> {
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Stop> {
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// provides `remaining` elements (or entries) of a sequence (or a map)
+struct Elements<'a> {
+    probe: Probe<'a>,
+    remaining: usize,
+    first: bool,
+}
+
+impl Elements<'_> {
+    #[inline]
+    fn next(&mut self) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+
+        if !self.first {
+            self.probe.write(",");
+        }
+        self.first = false;
+        self.remaining -= 1;
+        true
+    }
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_> {
+    type Error = Stop;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Stop> {
+        if !self.next() {
+            return Ok(None);
+        }
+
+        seed.deserialize(self.probe).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> MapAccess<'de> for Elements<'_> {
+    type Error = Stop;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Stop> {
+        if !self.next() {
+            return Ok(None);
+        }
+
+        seed.deserialize(self.probe).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Stop> {
+        self.probe.write(":");
+        seed.deserialize(self.probe)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'a, 'de> EnumAccess<'de> for Probe<'a> {
+    type Error = Stop;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Stop> {
+        // the identifier of the first variant, nothing is recorded
+        let variant = seed.deserialize(self)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Probe<'_> {
+    type Error = Stop;
+
+    fn unit_variant(self) -> Result<(), Stop> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Stop> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Stop> {
+        self.elements(len, "(", ")", visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Stop> {
+        self.write("{");
+        self.write(&fields.join(","));
+        self.write("}");
+        self.elements(fields.len(), "(", ")", visitor)
+    }
+}
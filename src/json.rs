@@ -1,27 +1,223 @@
-use serde::{Deserialize, Serialize};
-use crate::{Base, error};
+use std::fmt::{Debug, Formatter};
+use std::sync::OnceLock;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{SeqAccess, Visitor};
+use serde::ser::SerializeStruct;
+use crate::{Base, Config};
+#[cfg(feature = "serde_json")]
+use crate::{Versioned, error};
+#[cfg(feature = "serde_json")]
+use crate::error::{ResultExt, Stage};
 
 pub type Format = Base;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Clone)]
 /// [Json] stores the data and encoding.
+///
+/// it is serialized as `{"f": format, "v": data}`. The human-readable formats (JSON, YAML, TOML,
+/// see [Serializer::is_human_readable]) have the data as the basex string, and the binary formats
+/// (MessagePack, CBOR, bincode, ...) have the payload as raw bytes, so the envelopes embedded in
+/// them do not pay for the base encoding.
+///
+/// the envelopes embedded by 1.1.0, which have the basex string in the binary formats too, are
+/// still read from bincode and MessagePack. CBOR (ciborium) reads the data as bytes only.
+///
+/// ```rust
+/// let json = serde_tran::to_json(&("alice", 42u32)).unwrap();
+///
+/// // the payload is embedded as is
+/// let bytes = bincode::serialize(&json).unwrap();
+/// let payload = serde_tran::to_vec(&("alice", 42u32)).unwrap();
+/// assert!(bytes.windows(payload.len()).any(|window| window == payload));
+///
+/// let restored: serde_tran::Json = bincode::deserialize(&bytes).unwrap();
+/// assert_eq!(restored.data(), json.data());
+/// ```
 pub struct Json {
-    #[serde(rename = "f")]
     pub(crate) _format: Format,
-    #[serde(rename = "v")]
     /// data is a basex string, see [Base]
     pub(crate) _data: String,
+    /// the decoded data, which the binary formats embed. it is kept when the envelope is read
+    /// from a binary format, and decoded once otherwise
+    _payload: OnceLock<Vec<u8>>,
+}
+
+impl Debug for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Json")
+            .field("_format", &self._format)
+            .field("_data", &self._data)
+            .finish()
+    }
+}
+
+impl Serialize for Json {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return Self::serialize_fields(self._format, &self._data, serializer);
+        }
+
+        let payload = match self._payload.get() {
+            Some(payload) => payload,
+            None => {
+                let payload = self._format.from_base(&self._data).map_err(serde::ser::Error::custom)?;
+                self._payload.get_or_init(|| payload)
+            },
+        };
+        Self::serialize_fields(self._format, &RawBytes(payload), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Raw {
+            #[serde(rename = "f")]
+            format: Format,
+            #[serde(rename = "v", deserialize_with = "Data::deserialize")]
+            data: Data,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        match raw.data {
+            Data::Text(data) => Ok(Self::new(raw.format, data)),
+            // the envelopes embedded by 1.1.0 have the basex string in the binary formats too. a
+            // payload is never a valid basex string: it starts with the magic and the version byte
+            // 1, or the little endian length (with zero high bytes), or the gzip header
+            Data::Bytes(bytes) => match raw.format.from_base(&bytes) {
+                Ok(payload) => {
+                    let data = String::from_utf8(bytes).map_err(serde::de::Error::custom)?;
+                    Ok(Self::with_payload(raw.format, data, payload))
+                },
+                Err(_) => {
+                    let data = crate::to_base(&bytes, raw.format).map_err(serde::de::Error::custom)?;
+                    Ok(Self::with_payload(raw.format, data, bytes))
+                },
+            },
+        }
+    }
+}
+
+/// the bytes which are serialized with [Serializer::serialize_bytes]
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// the data of [Json]: the basex string in the human-readable formats, or the payload in the others
+enum Data {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Data {
+    fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer).map(Data::Text)
+        } else {
+            deserializer.deserialize_byte_buf(DataVisitor)
+        }
+    }
+}
+
+struct DataVisitor;
+
+impl<'de> Visitor<'de> for DataVisitor {
+    type Value = Data;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("bytes or a basex string")
+    }
+
+    // the self-describing formats (MessagePack, CBOR) tell the strings of 1.1.0 from the bytes
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Data::Text(v.to_string()))
+    }
+
+    fn visit_string<E: serde::de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Data::Text(v))
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Data::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Data::Bytes(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(Data::Bytes(bytes))
+    }
 }
 
 impl Json {
+    /// the format used by [to_json]: [Base::Base64], or [Base::Base58] when base64 is not enabled
+    #[cfg(feature = "base64")]
+    pub(crate) const DEFAULT_FORMAT: Format = Base::Base64;
+    #[cfg(not(feature = "base64"))]
+    pub(crate) const DEFAULT_FORMAT: Format = Base::Base58;
+
+    /// the media type of the [Json] envelopes, to tell them from plain JSON in the HTTP headers
+    pub const MEDIA_TYPE: &'static str = "application/vnd.serde-tran+json";
+
     #[inline]
     pub(crate) fn new(format: Format, data: String) -> Self {
         Self {
             _format: format,
             _data: data,
+            _payload: OnceLock::new(),
         }
     }
 
+    #[inline]
+    fn with_payload(format: Format, data: String, payload: Vec<u8>) -> Self {
+        Self {
+            _format: format,
+            _data: data,
+            _payload: OnceLock::from(payload),
+        }
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// create [Json] from the bytes of [crate::to_vec]
+    #[inline]
+    pub(crate) fn from_payload(format: Format, payload: &[u8]) -> error::Result<Self> {
+        let data = crate::to_base(payload, format)?;
+        Ok(Self::new(format, data))
+    }
+
+    /// serialize the `{"f": format, "v": data}` fields, data is the basex string or the payload
+    fn serialize_fields<D, S>(format: Format, data: &D, serializer: S) -> Result<S::Ok, S::Error>
+        where D: Serialize + ?Sized, S: Serializer
+    {
+        let mut state = serializer.serialize_struct("Json", 2)?;
+        state.serialize_field("f", &format)?;
+        state.serialize_field("v", data)?;
+        state.end()
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// serialize value as a [Json] envelope (with the default [Config]), without building the
+    /// basex string for the binary formats
+    pub(crate) fn serialize_value<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: Serialize + ?Sized, S: Serializer
+    {
+        if serializer.is_human_readable() {
+            return Config::default().to_json(value).map_err(serde::ser::Error::custom)?.serialize(serializer);
+        }
+
+        let payload = Config::default().to_vec(value).map_err(serde::ser::Error::custom)?;
+        Self::serialize_fields(Self::DEFAULT_FORMAT, &RawBytes(&payload), serializer)
+    }
+
     /// return the format ([Format]) of the [data] function
     #[inline]
     pub fn format(&self) -> Format {
@@ -37,14 +233,14 @@ impl Json {
     #[cfg(feature = "serde_json")]
     /// return the json string, using [serde_json::to_string]
     pub fn to_string(&self) -> error::Result<String> {
-        let data = serde_json::to_string(&self)?;
+        let data = serde_json::to_string(&self).at(Stage::Encode)?;
         Ok(data)
     }
 
     #[cfg(feature = "serde_json")]
     /// return the bytes (in json format), using [serde_json::to_vec]
     pub fn to_vec(&self) -> error::Result<Vec<u8>> {
-        let bytes = serde_json::to_vec(&self)?;
+        let bytes = serde_json::to_vec(&self).at(Stage::Encode)?;
         Ok(bytes)
     }
 
@@ -52,22 +248,77 @@ impl Json {
     /// convert [Self] back to user data T
     pub fn to_value<T>(&self) -> error::Result<T>
         where T: for<'de> Deserialize<'de>
+    {
+        self.to_value_with(&Config::default())
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// convert [Self] back to user data T, with the [crate::Limits] in config
+    pub fn to_value_with<T>(&self, config: &Config) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
     {
         match self.format() {
             Base::Base58 => {
                 #[cfg(feature = "bs58")]
-                { crate::from_base58(self.data()) }
+                { config.from_base58(self.data()) }
                 #[cfg(not(feature = "bs58"))]
-                { Err(error::ErrorKind::CustomError("unknown data format".to_string())) }
+                { Err(error::ErrorKind::FeatureDisabled("bs58")).at(Stage::BaseDecode) }
             },
             Base::Base64 => {
                 #[cfg(feature = "base64")]
-                { crate::from_base64(self.data()) }
+                { config.from_base64(self.data()) }
                 #[cfg(not(feature = "base64"))]
-                { Err(error::ErrorKind::CustomError("unknown data format".to_string())) }
+                { Err(error::ErrorKind::FeatureDisabled("base64")).at(Stage::BaseDecode) }
             },
         }
     }
+
+    #[cfg(feature = "serde_json")]
+    /// convert [Self] back to user data T, the payloads of older versions are upgraded,
+    /// see [crate::from_slice_versioned]
+    pub fn to_value_versioned<T>(&self) -> error::Result<T>
+        where T: Versioned
+    {
+        self.to_value_versioned_with(&Config::default())
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// convert [Self] back to user data T like [Json::to_value_versioned], with the [crate::Limits] in config
+    pub fn to_value_versioned_with<T>(&self, config: &Config) -> error::Result<T>
+        where T: Versioned
+    {
+        let bytes = config.decode_base(self.data(), self.format())?;
+        config.from_slice_versioned(&bytes)
+    }
+}
+
+impl Config {
+    #[cfg(feature = "serde_json")]
+    /// convert given data into [Json], see [to_json]
+    pub fn to_json<T>(&self, data: &T) -> error::Result<Json>
+        where T: Serialize + ?Sized
+    {
+        let bytes = self.to_vec(data)?;
+        Json::from_payload(Json::DEFAULT_FORMAT, &bytes)
+    }
+
+    #[cfg(all(feature = "serde_json", feature = "base64"))]
+    /// convert given data into [Json], where format is [Base::Base64]
+    pub fn to_json_base64<T>(&self, data: &T) -> error::Result<Json>
+        where T: Serialize + ?Sized
+    {
+        let data = self.to_base64(data)?;
+        Ok(Json::new(Base::Base64, data))
+    }
+
+    #[cfg(all(feature = "serde_json", feature = "bs58"))]
+    /// convert given data into [Json], where format is [Base::Base58]
+    pub fn to_json_base58<T>(&self, data: &T) -> error::Result<Json>
+        where T: Serialize + ?Sized
+    {
+        let data = self.to_base58(data)?;
+        Ok(Json::new(Base::Base58, data))
+    }
 }
 
 #[cfg(feature = "serde_json")]
@@ -78,17 +329,7 @@ impl Json {
 pub fn to_json<T>(data: &T) -> error::Result<Json>
     where T: Serialize
 {
-    #[cfg(feature = "base64")]
-    {
-        return to_json_base64(data);
-    }
-
-    #[cfg(feature = "bs58")]
-    {
-        return to_json_base58(data);
-    }
-
-    unreachable!("when serde_json is enabled, base64 and bs58 should be enabled at least one");
+    Config::default().to_json(data)
 }
 
 #[cfg(all(feature = "serde_json", feature = "base64"))]
@@ -96,8 +337,7 @@ pub fn to_json<T>(data: &T) -> error::Result<Json>
 pub fn to_json_base64<T>(data: &T) -> error::Result<Json>
     where T: Serialize
 {
-    let data = crate::to_base64(data)?;
-    Ok(Json::new(Base::Base64, data))
+    Config::default().to_json_base64(data)
 }
 
 #[cfg(all(feature = "serde_json", feature = "bs58"))]
@@ -105,14 +345,13 @@ pub fn to_json_base64<T>(data: &T) -> error::Result<Json>
 pub fn to_json_base58<T>(data: &T) -> error::Result<Json>
     where T: Serialize
 {
-    let data = crate::to_base58(data)?;
-    Ok(Json::new(Base::Base58, data))
+    Config::default().to_json_base58(data)
 }
 
 #[cfg(feature = "serde_json")]
 /// convert bytes back to [Json], then you can use [Json::to_value] to get your custom data.
 pub fn from_json_slice<T: AsRef<[u8]>>(bytes: T) -> error::Result<Json> {
-    let json: Json = serde_json::from_slice(bytes.as_ref())?;
+    let json: Json = serde_json::from_slice(bytes.as_ref()).at(Stage::EnvelopeParse)?;
 
     Ok(json)
 }
@@ -50,17 +50,75 @@
 mod error;
 pub use error::*;
 
+mod config;
+mod codec;
+mod limit;
+mod clock;
+mod track;
 mod core;
 mod basex;
 mod json;
+mod verify;
+mod fingerprint;
+mod versioned;
+mod registry;
+mod replay;
+mod media;
+mod binary;
+#[cfg(feature = "serde_json")]
+mod field;
+#[cfg(any(feature = "serde_json", feature = "base64"))]
+mod with;
+#[cfg(feature = "base64")]
+mod cookie;
+#[cfg(feature = "base64")]
+mod armor;
+#[cfg(feature = "token")]
+mod token;
+#[cfg(all(feature = "serde_json", feature = "base64"))]
+mod stream;
+#[cfg(feature = "actix-web")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "reqwest")]
+pub mod reqwest;
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod benchmarks;
 
+pub use config::*;
+pub use codec::*;
+pub use limit::*;
+pub use clock::*;
+pub use track::{Path, Segment};
 pub use core::*;
 pub use basex::*;
 pub use json::*;
+pub use verify::*;
+pub use fingerprint::*;
+pub use versioned::*;
+pub use registry::*;
+pub use replay::*;
+pub use media::*;
+pub use binary::*;
+#[cfg(feature = "serde_json")]
+pub use field::*;
+#[cfg(any(feature = "serde_json", feature = "base64"))]
+pub use with::*;
+#[cfg(feature = "base64")]
+pub use cookie::*;
+#[cfg(feature = "base64")]
+pub use armor::*;
+#[cfg(feature = "token")]
+pub use token::*;
+#[cfg(all(feature = "serde_json", feature = "base64"))]
+pub use stream::*;
 
 #[cfg(all(feature = "serde_json", not(any(feature = "bs58", feature = "base64"))))]
 compile_error!("feature `serde_json` required at least one of bs58 and base64");
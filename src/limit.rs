@@ -0,0 +1,115 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Deserializer};
+use crate::error;
+
+/// [Limits] bounds the resources used when decoding untrusted payloads,
+/// by default there is no limit.
+///
+/// not every codec can enforce every limit, the ones which are ignored without an error are:
+/// - [Limits::allocation], which is only enforced by [crate::CodecId::Bincode]
+/// - [Limits::depth], which is ignored by [crate::CodecId::Bitcode]
+///
+/// so bound the payloads of the other codecs with [Limits::decoded_length] and [Limits::decompressed_size].
+///
+/// ```rust
+/// use serde_tran::{Config, ErrorKind, Limit, Limits};
+///
+/// let config = Config::new().limits(Limits::new().decoded_length(16));
+///
+/// let bytes = serde_tran::to_vec(&vec![0u8; 1024]).unwrap();
+/// let result = config.from_slice::<Vec<u8>>(&bytes);
+/// assert!(matches!(result.unwrap_err().kind(), ErrorKind::LimitExceeded(Limit::DecodedLength(16))));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Limits {
+    pub(crate) decoded_length: Option<u64>,
+    pub(crate) decompressed_size: Option<u64>,
+    pub(crate) allocation: Option<u64>,
+    pub(crate) depth: Option<usize>,
+}
+
+impl Limits {
+    /// create limits without any limit
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the max length (in bytes) of the payload, after base decoding (see [Limit::DecodedLength])
+    #[inline]
+    pub fn decoded_length(mut self, max: u64) -> Self {
+        self.decoded_length = Some(max);
+        self
+    }
+
+    /// the max size (in bytes) of the decompressed payload (see [Limit::DecompressedSize])
+    #[inline]
+    pub fn decompressed_size(mut self, max: u64) -> Self {
+        self.decompressed_size = Some(max);
+        self
+    }
+
+    /// the max size (in bytes) that bincode may allocate, see [bincode::Options::with_limit]
+    /// (and [Limit::Allocation])
+    ///
+    /// note: only [crate::CodecId::Bincode] supports this limit, the other codecs ignore it.
+    #[inline]
+    pub fn allocation(mut self, max: u64) -> Self {
+        self.allocation = Some(max);
+        self
+    }
+
+    /// the max nesting depth of the data (see [Limit::Depth]).
+    /// every sequence, map, enum, `Some` and newtype struct is one level.
+    ///
+    /// note: [crate::CodecId::Bitcode] does not support this limit, it ignores it.
+    #[inline]
+    pub fn depth(mut self, max: usize) -> Self {
+        self.depth = Some(max);
+        self
+    }
+
+    /// check the length of the payload, or the upper bound of it before base decoding
+    #[inline]
+    pub(crate) fn check_decoded_length(&self, length: u64) -> error::Result<()> {
+        match self.decoded_length {
+            Some(max) if length > max => Err(error::ErrorKind::LimitExceeded(Limit::DecodedLength(max)).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// deserialize T from the deserializer, with [Limits::depth] applied and the [crate::Path] tracked
+    #[inline]
+    pub(crate) fn deserialize<'de, D, T>(&self, deserializer: D) -> error::Result<T>
+        where
+            D: Deserializer<'de>,
+            T: Deserialize<'de>,
+            error::Error: From<D::Error>,
+    {
+        crate::track::deserialize(deserializer, self.depth)
+    }
+}
+
+/// [Limit] is the limit which is exceeded, with the max value in [Limits]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Limit {
+    /// the payload (after base decoding) is longer than the max length
+    DecodedLength(u64),
+    /// the decompressed payload is larger than the max size
+    DecompressedSize(u64),
+    /// bincode tries to allocate more than the max size
+    Allocation(u64),
+    /// the data is nested deeper than the max depth
+    Depth(usize),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DecodedLength(max) => write!(f, "decoded length is larger than {} bytes", max),
+            Self::DecompressedSize(max) => write!(f, "decompressed size is larger than {} bytes", max),
+            Self::Allocation(max) => write!(f, "allocation is larger than {} bytes", max),
+            Self::Depth(max) => write!(f, "nesting depth is larger than {}", max),
+        }
+    }
+}
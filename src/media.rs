@@ -0,0 +1,343 @@
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "serde_json")]
+use serde::{Deserialize, Serialize};
+use crate::{Base, Binary, CodecId, Config, Json};
+#[cfg(feature = "serde_json")]
+use crate::error;
+
+/// [MediaType] is the media type of the [Json] envelopes: [Json::MEDIA_TYPE], with the optional
+/// parameters `base` (`base64` or `base58`), `codec` (see [CodecId::name]) and `compression`
+/// (`gzip` or `none`):
+///
+/// ```text
+/// application/vnd.serde-tran+json; base=base64; codec=bincode; compression=gzip
+/// ```
+///
+/// or of the [Binary] envelopes: [Binary::MEDIA_TYPE], with the parameters `codec` and `compression`.
+///
+/// the parameters tell the clients and proxies what is inside, the decoders never need them
+/// (the payload records the codec and options).
+///
+/// ```rust
+/// use serde_tran::{CodecId, Config, MediaType};
+///
+/// let media_type = Config::new().codec(CodecId::Bincode).media_type();
+/// let content_type = media_type.to_string();
+///
+/// let parsed = MediaType::from_content_type(&content_type).unwrap();
+/// assert_eq!(parsed.get_codec(), Some(CodecId::Bincode));
+/// assert!(MediaType::from_content_type("application/json").is_none());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MediaType {
+    binary: bool,
+    base: Option<Base>,
+    codec: Option<CodecId>,
+    compressed: Option<bool>,
+}
+
+impl MediaType {
+    /// [Json::MEDIA_TYPE] without parameters
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// [Binary::MEDIA_TYPE] without parameters
+    #[inline]
+    pub fn binary() -> Self {
+        Self { binary: true, ..Self::default() }
+    }
+
+    /// whether it is the media type of the [Binary] envelopes
+    #[inline]
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    /// set the `base` parameter, which is ignored by the [Binary] envelopes
+    #[inline]
+    pub fn base(mut self, base: Base) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    /// set the `codec` parameter
+    #[inline]
+    pub fn codec(mut self, codec: CodecId) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// set the `compression` parameter, `gzip` or `none`
+    #[inline]
+    pub fn compressed(mut self, compressed: bool) -> Self {
+        self.compressed = Some(compressed);
+        self
+    }
+
+    /// the `base` parameter
+    #[inline]
+    pub fn get_base(&self) -> Option<Base> {
+        self.base
+    }
+
+    /// the `codec` parameter
+    #[inline]
+    pub fn get_codec(&self) -> Option<CodecId> {
+        self.codec
+    }
+
+    /// the `compression` parameter, `Some(true)` for `gzip`
+    #[inline]
+    pub fn is_compressed(&self) -> Option<bool> {
+        self.compressed
+    }
+
+    /// parse the value of a Content-Type header, `None` when it is neither [Json::MEDIA_TYPE]
+    /// nor [Binary::MEDIA_TYPE].
+    ///
+    /// the unknown parameters (and the unknown values of the known ones) are ignored
+    pub fn from_content_type(value: &str) -> Option<Self> {
+        let mut parts = value.split(';');
+        let essence = parts.next()?.trim();
+        let mut media_type = if essence.eq_ignore_ascii_case(Json::MEDIA_TYPE) {
+            Self::new()
+        } else if essence.eq_ignore_ascii_case(Binary::MEDIA_TYPE) {
+            Self::binary()
+        } else {
+            return None;
+        };
+
+        for (name, value) in parts.filter_map(parameter) {
+            match name.to_ascii_lowercase().as_str() {
+                "base" => media_type.base = base_of(&value),
+                "codec" => media_type.codec = codec_of(&value),
+                "compression" => media_type.compressed = match value.to_ascii_lowercase().as_str() {
+                    "gzip" => Some(true),
+                    "none" => Some(false),
+                    _ => None,
+                },
+                _ => {},
+            }
+        }
+
+        Some(media_type)
+    }
+}
+
+impl Display for MediaType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.binary {
+            f.write_str(Binary::MEDIA_TYPE)?;
+        } else {
+            f.write_str(Json::MEDIA_TYPE)?;
+            if let Some(base) = self.base {
+                write!(f, "; base={}", base_name(base))?;
+            }
+        }
+        if let Some(codec) = self.codec {
+            write!(f, "; codec={}", codec.name())?;
+        }
+        if let Some(compressed) = self.compressed {
+            write!(f, "; compression={}", if compressed { "gzip" } else { "none" })?;
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// the [MediaType] of the [Json] envelopes of this config, with all the parameters
+    pub fn media_type(&self) -> MediaType {
+        MediaType::new()
+            .base(Json::DEFAULT_FORMAT)
+            .codec(self.codec)
+            .compressed(cfg!(feature = "flate2"))
+    }
+
+    /// the [MediaType] of the [Binary] envelopes of this config, with all the parameters
+    pub fn binary_media_type(&self) -> MediaType {
+        MediaType::binary()
+            .codec(self.codec)
+            .compressed(cfg!(feature = "flate2"))
+    }
+
+    /// convert data into the envelope the client prefers: [Binary] if [Accept::prefers_binary],
+    /// or [Json] otherwise. returns the body and its content type
+    #[cfg(feature = "serde_json")]
+    pub fn to_body<T>(&self, data: &T, accept: &Accept) -> error::Result<(Vec<u8>, MediaType)>
+        where T: Serialize + ?Sized
+    {
+        if accept.prefers_binary() {
+            Ok((self.to_vec(data)?, self.binary_media_type()))
+        } else {
+            Ok((self.to_json(data)?.to_vec()?, self.media_type()))
+        }
+    }
+
+    /// convert the body of an envelope back to T: [Binary] if the content type is
+    /// [Binary::MEDIA_TYPE], or [Json] otherwise
+    #[cfg(feature = "serde_json")]
+    pub fn from_body<T>(&self, content_type: Option<&str>, body: &[u8]) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        let is_binary = content_type.and_then(MediaType::from_content_type).is_some_and(|media_type| media_type.is_binary());
+        if is_binary {
+            self.from_slice(body)
+        } else {
+            crate::from_json_slice(body)?.to_value_with(self)
+        }
+    }
+}
+
+/// the conversions of the middlewares (`actix::Transparent` and `tower::TranLayer`), which do not
+/// know the types of the handlers, so they convert between plain JSON and envelopes by [serde_json::Value]
+#[cfg(all(feature = "serde_json", any(feature = "actix-web", feature = "tower")))]
+impl Config {
+    /// the codec should be self-describing, so the envelopes can be decoded without the types
+    pub(crate) fn check_self_describing(&self) -> error::Result<()> {
+        if !self.codec.is_self_describing() {
+            return Err(error::ErrorKind::CustomError(format!(
+                "codec {} is not self-describing, use CodecId::MessagePack or CodecId::Cbor", self.codec.name(),
+            )).into());
+        }
+
+        Ok(())
+    }
+
+    /// convert the body of an envelope into plain JSON, see [Config::from_body]
+    pub(crate) fn envelope_to_json(&self, content_type: Option<&str>, body: &[u8]) -> error::Result<Vec<u8>> {
+        let value: serde_json::Value = self.from_body(content_type, body)?;
+        Ok(serde_json::to_vec(&value)?)
+    }
+
+    /// convert plain JSON into the envelope the client prefers, see [Config::to_body]
+    pub(crate) fn json_to_envelope(&self, body: &[u8], accept: &Accept) -> error::Result<(Vec<u8>, MediaType)> {
+        let value: serde_json::Value = serde_json::from_slice(body)?;
+        self.to_body(&value, accept)
+    }
+}
+
+/// [Accept] is the parsed Accept header, which tells whether the client prefers the [Json]
+/// (or [Binary]) envelopes to plain JSON.
+///
+/// ```rust
+/// use serde_tran::Accept;
+///
+/// assert!(Accept::parse("application/vnd.serde-tran+json, application/json;q=0.9").prefers_tran());
+/// assert!(!Accept::parse("application/json").prefers_tran());
+/// // the wildcards do not opt in to the envelopes
+/// assert!(!Accept::parse("*/*").prefers_tran());
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Accept {
+    /// the lowercase media ranges, with the qualities in thousandths
+    ranges: Vec<(String, u16)>,
+}
+
+impl Accept {
+    /// parse the value of an Accept header, the invalid media ranges are ignored
+    pub fn parse(value: &str) -> Self {
+        Self::parse_all([value])
+    }
+
+    /// parse the values of the Accept headers of a request
+    pub fn parse_all<'a, I>(values: I) -> Self
+        where I: IntoIterator<Item = &'a str>
+    {
+        let ranges = values.into_iter()
+            .flat_map(|value| value.split(','))
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let essence = parts.next()?.trim().to_ascii_lowercase();
+                if !essence.contains('/') {
+                    return None;
+                }
+
+                let quality = parts.filter_map(parameter)
+                    .find(|(name, _)| name.eq_ignore_ascii_case("q"))
+                    .map(|(_, value)| value.parse::<f32>().map(|q| (q.clamp(0.0, 1.0) * 1000.0).round() as u16))
+                    .unwrap_or(Ok(1000))
+                    .ok()?;
+                Some((essence, quality))
+            })
+            .collect();
+
+        Self { ranges }
+    }
+
+    /// the quality (from 0 to 1) of media_type, of the most specific media range which matches it.
+    /// everything is acceptable (1) when there is no media range
+    pub fn quality(&self, media_type: &str) -> f32 {
+        self.quality_of(media_type, true) as f32 / 1000.0
+    }
+
+    /// whether the client lists [Json::MEDIA_TYPE] explicitly (not by a wildcard), with a quality
+    /// no lower than `application/json`
+    pub fn prefers_tran(&self) -> bool {
+        let tran = self.quality_of(Json::MEDIA_TYPE, false);
+        tran > 0 && tran >= self.quality_of("application/json", true)
+    }
+
+    /// whether the client lists [Binary::MEDIA_TYPE] explicitly (not by a wildcard), with a quality
+    /// no lower than [Json::MEDIA_TYPE] and `application/json`
+    pub fn prefers_binary(&self) -> bool {
+        let binary = self.quality_of(Binary::MEDIA_TYPE, false);
+        binary > 0
+            && binary >= self.quality_of(Json::MEDIA_TYPE, false)
+            && binary >= self.quality_of("application/json", true)
+    }
+
+    fn quality_of(&self, media_type: &str, wildcard: bool) -> u16 {
+        if self.ranges.is_empty() {
+            return if wildcard { 1000 } else { 0 };
+        }
+
+        let media_type = media_type.to_ascii_lowercase();
+        let main_type = media_type.split('/').next().unwrap_or_default();
+
+        // exact > type/* > */*
+        let mut best: Option<(u8, u16)> = None;
+        for (range, quality) in &self.ranges {
+            let specificity = if *range == media_type {
+                2
+            } else if wildcard && range.strip_suffix("/*") == Some(main_type) {
+                1
+            } else if wildcard && range == "*/*" {
+                0
+            } else {
+                continue;
+            };
+
+            if best.is_none_or(|(best, _)| specificity > best) {
+                best = Some((specificity, *quality));
+            }
+        }
+
+        best.map(|(_, quality)| quality).unwrap_or(0)
+    }
+}
+
+/// split `name=value` (the value may be quoted)
+fn parameter(part: &str) -> Option<(String, String)> {
+    let (name, value) = part.split_once('=')?;
+    let value = value.trim().trim_matches('"');
+    Some((name.trim().to_string(), value.to_string()))
+}
+
+fn base_name(base: Base) -> &'static str {
+    match base {
+        Base::Base58 => "base58",
+        Base::Base64 => "base64",
+    }
+}
+
+fn base_of(name: &str) -> Option<Base> {
+    [Base::Base58, Base::Base64].into_iter().find(|base| base_name(*base).eq_ignore_ascii_case(name))
+}
+
+fn codec_of(name: &str) -> Option<CodecId> {
+    [CodecId::Bincode, CodecId::MessagePack, CodecId::Postcard, CodecId::Bitcode, CodecId::Cbor]
+        .into_iter()
+        .find(|codec| codec.name().eq_ignore_ascii_case(name))
+}
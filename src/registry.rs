@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::{Config, Content, Extensions, Limits, error};
+use crate::error::{ResultExt, Stage};
+
+/// [Tagged] is a message type with a tag, which is recorded in the payloads written by the
+/// `*_tagged` functions (such as [Config::to_vec_tagged]), so a [Registry] can decode the
+/// payload without knowing the type in advance.
+///
+/// the tag should be unique among the message types, and at most 255 bytes.
+pub trait Tagged: Serialize + for<'de> Deserialize<'de> + 'static {
+    /// the tag recorded in the payload
+    const TAG: &'static str;
+}
+
+type Decoder<R> = Box<dyn Fn(&Content<'_>, &Limits) -> error::Result<R> + Send + Sync>;
+
+/// [Registry] maps the tags of [Tagged] types to decoders, each decoder converts the
+/// message into R, such as a variant of a user enum, or the result of a handler.
+///
+/// the handlers are `Send + Sync`, so a registry can be shared between threads, such as in
+/// the state of a web server.
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_tran::{Registry, Tagged};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Login { username: String }
+/// impl Tagged for Login { const TAG: &'static str = "login"; }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Logout;
+/// impl Tagged for Logout { const TAG: &'static str = "logout"; }
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Message { Login(Login), Logout(Logout) }
+///
+/// let registry = Registry::new()
+///     .register(Message::Login)
+///     .register(Message::Logout);
+///
+/// let json = serde_tran::to_json_tagged(&Logout).unwrap();
+/// assert_eq!(registry.decode(&json).unwrap(), Message::Logout(Logout));
+/// ```
+pub struct Registry<R> {
+    decoders: HashMap<&'static str, Decoder<R>>,
+}
+
+impl<R> Default for Registry<R> {
+    fn default() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+}
+
+impl<R> Registry<R> {
+    /// create a registry without any type
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register T, its messages are converted into R with handler
+    ///
+    /// # Panics
+    ///
+    /// panics if the tag of T is registered already
+    pub fn register<T, F>(mut self, handler: F) -> Self
+        where
+            T: Tagged,
+            F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        let decode: Decoder<R> = Box::new(move |content, limits| content.decode(limits).map(&handler));
+        let registered = self.decoders.insert(T::TAG, decode);
+        assert!(registered.is_none(), "tag {} is registered already", T::TAG);
+
+        self
+    }
+
+    /// whether the tag is registered
+    #[inline]
+    pub fn contains(&self, tag: &str) -> bool {
+        self.decoders.contains_key(tag)
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// decode the message in [crate::Json] with the decoder of its tag
+    pub fn decode(&self, json: &crate::Json) -> error::Result<R> {
+        self.decode_with(json, &Config::default())
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// decode the message in [crate::Json] like [Registry::decode], with the [crate::Limits] in config
+    pub fn decode_with(&self, json: &crate::Json, config: &Config) -> error::Result<R> {
+        let bytes = config.decode_base(json.data(), json.format())?;
+        self.decode_slice_with(&bytes, config)
+    }
+
+    /// decode the message in bytes with the decoder of its tag
+    pub fn decode_slice(&self, slice: &[u8]) -> error::Result<R> {
+        self.decode_slice_with(slice, &Config::default())
+    }
+
+    /// decode the message in bytes like [Registry::decode_slice], with the [crate::Limits] in config
+    pub fn decode_slice_with(&self, slice: &[u8], config: &Config) -> error::Result<R> {
+        config.decode_slice(slice, |content| {
+            let tag = content.extensions.tag.ok_or(error::ErrorKind::MissingTag).at(Stage::Header)?;
+            match self.decoders.get(tag) {
+                Some(decode) => decode(content, &config.limits),
+                None => Err(error::ErrorKind::UnknownTag(tag.to_string())).at(Stage::Header),
+            }
+        })
+    }
+}
+
+impl Config {
+    /// convert data to bytes like [Config::to_vec], with [Tagged::TAG] recorded. see [to_vec_tagged]
+    pub fn to_vec_tagged<T>(&self, data: &T) -> error::Result<Vec<u8>>
+        where T: Tagged
+    {
+        let mut bytes = Vec::new();
+        let extensions = Extensions { tag: Some(T::TAG), ..Extensions::default() };
+        self.write_payload(&mut bytes, data, &extensions)?;
+
+        Ok(bytes)
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// convert given data into [crate::Json] like [Config::to_json], with [Tagged::TAG] recorded
+    pub fn to_json_tagged<T>(&self, data: &T) -> error::Result<crate::Json>
+        where T: Tagged
+    {
+        let bytes = self.to_vec_tagged(data)?;
+        crate::Json::from_payload(crate::Json::DEFAULT_FORMAT, &bytes)
+    }
+}
+
+/// convert data to bytes, with [Tagged::TAG] recorded, so the bytes can be decoded by a [Registry]
+pub fn to_vec_tagged<T>(data: &T) -> error::Result<Vec<u8>>
+    where T: Tagged
+{
+    Config::default().to_vec_tagged(data)
+}
+
+#[cfg(feature = "serde_json")]
+/// convert given data into [crate::Json], with [Tagged::TAG] recorded. see [to_vec_tagged]
+pub fn to_json_tagged<T>(data: &T) -> error::Result<crate::Json>
+    where T: Tagged
+{
+    Config::default().to_json_tagged(data)
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::{Config, error};
+use crate::error::{ResultExt, Stage};
+
+/// [Nonce] is a unique value and the time it is issued at, which are embedded in the payload
+/// when [Config::nonce] is enabled, so the receiver can reject replayed payloads
+/// (see [Config::replay_protection]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Nonce {
+    value: [u8; Nonce::SIZE],
+    /// unix time in seconds
+    issued_at: u64,
+}
+
+impl Nonce {
+    /// the size of the unique value
+    pub const SIZE: usize = 16;
+
+    /// generate a unique nonce issued at now.
+    ///
+    /// the value is not a cryptographic random number, it is only unique: the payload is not
+    /// secret, and it is the MAC (if any) which stops the nonce from being forged.
+    pub(crate) fn generate(now: SystemTime) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_nanos()).unwrap_or(0);
+
+        let mut value = [0u8; Self::SIZE];
+        for (i, chunk) in value.chunks_mut(8).enumerate() {
+            // every RandomState is seeded with different random keys
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(count);
+            hasher.write_usize(i);
+            hasher.write_u128(nanos);
+            hasher.write_u32(std::process::id());
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+
+        Self {
+            value,
+            issued_at: now.duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0),
+        }
+    }
+
+    /// the unique value
+    #[inline]
+    pub fn value(&self) -> &[u8; Nonce::SIZE] {
+        &self.value
+    }
+
+    /// the time it is issued at, in seconds
+    #[inline]
+    pub fn issued_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.issued_at)
+    }
+
+    /// write the nonce: the value, and the time it is issued at (8 bytes, little endian)
+    pub(crate) fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&self.value)?;
+        writer.write_all(&self.issued_at.to_le_bytes())
+    }
+
+    /// read the nonce written by [Self::write_to], return it and the rest bytes
+    pub(crate) fn read_from(bytes: &[u8]) -> error::Result<(Self, &[u8])> {
+        let truncated = || error::ErrorKind::CustomError("payload is truncated".to_string());
+
+        let (value, bytes) = bytes.split_first_chunk::<{ Nonce::SIZE }>().ok_or_else(truncated)?;
+        let (issued_at, bytes) = bytes.split_first_chunk::<8>().ok_or_else(truncated)?;
+
+        let nonce = Self {
+            value: *value,
+            issued_at: u64::from_le_bytes(*issued_at),
+        };
+        Ok((nonce, bytes))
+    }
+}
+
+/// [NonceStore] remembers the nonces which are seen, see [Config::replay_protection].
+pub trait NonceStore: Send + Sync {
+    /// remember the nonce until expires_at, return false if it is seen and not expired yet
+    fn insert(&self, nonce: &Nonce, now: SystemTime, expires_at: SystemTime) -> bool;
+
+    /// forget the nonce, it is called when the payload with the nonce fails to decode, before
+    /// the decoded data is used
+    fn remove(&self, nonce: &Nonce);
+}
+
+/// [MemoryNonceStore] is a [NonceStore] in memory, the expired nonces are removed from time to time.
+///
+/// it only protects a single process, use a shared store (such as a database) for a cluster.
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    inner: Mutex<MemoryNonces>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryNonces {
+    seen: HashMap<[u8; Nonce::SIZE], SystemTime>,
+    /// remove the expired nonces when there are more nonces than this
+    purge_at: usize,
+}
+
+impl MemoryNonceStore {
+    const MIN_PURGE_AT: usize = 1024;
+
+    /// create an empty store
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the number of nonces in the store, including the expired ones which are not removed yet
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner()).seen.len()
+    }
+
+    /// whether the store is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn insert(&self, nonce: &Nonce, now: SystemTime, expires_at: SystemTime) -> bool {
+        let mut inner = self.inner.lock().unwrap_or_else(|err| err.into_inner());
+
+        if inner.seen.len() >= inner.purge_at {
+            inner.seen.retain(|_, expires_at| *expires_at > now);
+            inner.purge_at = (inner.seen.len() * 2).max(Self::MIN_PURGE_AT);
+        }
+
+        match inner.seen.get(nonce.value()) {
+            Some(seen_expires_at) if *seen_expires_at > now => false,
+            _ => {
+                inner.seen.insert(*nonce.value(), expires_at);
+                true
+            },
+        }
+    }
+
+    fn remove(&self, nonce: &Nonce) {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner()).seen.remove(nonce.value());
+    }
+}
+
+/// [Replay] is the replay protection in [Config]
+#[derive(Clone)]
+pub(crate) struct Replay {
+    window: Duration,
+    store: Arc<dyn NonceStore>,
+}
+
+impl Debug for Replay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Replay").field("window", &self.window).finish_non_exhaustive()
+    }
+}
+
+impl Config {
+    /// embed a [Nonce] in the payloads, default is false. see [Config::replay_protection]
+    #[inline]
+    pub fn nonce(mut self, nonce: bool) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// reject the payloads without a [Nonce] ([error::ErrorKind::MissingNonce]), the payloads
+    /// issued more than window ago ([error::ErrorKind::Expired]), and the payloads whose nonce is
+    /// seen in the store ([error::ErrorKind::Replayed]). the time is read from [Config::clock].
+    ///
+    /// the nonce is only protected by the hash of the payload, so sign the payloads (for example
+    /// with [crate::to_token], feature `token`) when the sender is not trusted.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use serde_tran::{Config, ErrorKind, MemoryNonceStore};
+    ///
+    /// let sender = Config::new().nonce(true);
+    /// let receiver = Config::new().replay_protection(Duration::from_secs(300), MemoryNonceStore::new());
+    ///
+    /// let bytes = sender.to_vec("transfer 100 coins").unwrap();
+    /// assert!(receiver.from_slice::<String>(&bytes).is_ok());
+    ///
+    /// let err = receiver.from_slice::<String>(&bytes).unwrap_err();
+    /// assert!(matches!(err.kind(), ErrorKind::Replayed));
+    /// ```
+    #[inline]
+    pub fn replay_protection<S>(mut self, window: Duration, store: S) -> Self
+        where S: NonceStore + 'static
+    {
+        self.replay = Some(Replay { window, store: Arc::new(store) });
+        self
+    }
+
+    /// the nonce embedded in the payloads written with this config
+    #[inline]
+    pub(crate) fn new_nonce(&self) -> Option<Nonce> {
+        self.nonce.then(|| Nonce::generate(self.clock.now()))
+    }
+
+    /// check the nonce of the payload before decoding it, if [Config::replay_protection] is set:
+    /// it is rejected when it expires, or it is seen in the store, otherwise it is recorded.
+    ///
+    /// the nonce is recorded before the data is decoded (and before any handler of a
+    /// [crate::Registry] runs), see [Config::forget_nonce] for the payloads which fail to decode
+    pub(crate) fn check_replay(&self, nonce: Option<&Nonce>) -> error::Result<()> {
+        let Some(replay) = &self.replay else {
+            return Ok(());
+        };
+
+        let nonce = nonce.ok_or(error::ErrorKind::MissingNonce).at(Stage::Validate)?;
+
+        let now = self.clock.now();
+        let expires_at = nonce.issued_at() + replay.window;
+        if now >= expires_at {
+            return Err(error::ErrorKind::Expired(expires_at)).at(Stage::Validate);
+        }
+        if nonce.issued_at() > now + replay.window {
+            return Err(error::ErrorKind::CustomError("nonce is issued in the future".to_string())).at(Stage::Validate);
+        }
+
+        if !replay.store.insert(nonce, now, expires_at) {
+            return Err(error::ErrorKind::Replayed).at(Stage::Validate);
+        }
+
+        Ok(())
+    }
+
+    /// remove the nonce recorded by [Config::check_replay] from the store, when the data fails to
+    /// decode (for example with a type mismatch), so the payload can be retried
+    pub(crate) fn forget_nonce(&self, nonce: Option<&Nonce>) {
+        if let (Some(replay), Some(nonce)) = (&self.replay, nonce) {
+            replay.store.remove(nonce);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! [reqwest](https://docs.rs/reqwest/0.12) integration (feature `reqwest`).
+//!
+//! [RequestBuilderExt::tran] sends the data in a [crate::Json] envelope, and asks for a
+//! [crate::Binary] envelope in the response (falling back to a [crate::Json] envelope, then plain
+//! JSON), and [ResponseExt::tran] decodes the response body, whichever of them it is.
+//!
+//! ```rust,no_run
+//! use serde::{Deserialize, Serialize};
+//! use serde_tran::reqwest::{RequestBuilderExt, ResponseExt};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct LoginRequest { username: String, password: String }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct LoginResponse { message: String }
+//!
+//! # async fn run() -> Result<(), serde_tran::reqwest::ClientError> {
+//! let request = LoginRequest { username: "alice".to_string(), password: "secret".to_string() };
+//! let response: LoginResponse = reqwest::Client::new()
+//!     .post("http://127.0.0.1:8080/login")
+//!     .tran(&request)?
+//!     .send()
+//!     .await?
+//!     .tran()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use ::reqwest::header::{ACCEPT, CONTENT_TYPE};
+use ::reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use crate::{Binary, Config, Json, error};
+
+/// [RequestBuilderExt] sends data in [crate::Json] envelopes
+pub trait RequestBuilderExt: Sized {
+    /// set the body to the envelope of data, see [RequestBuilderExt::tran_with]
+    fn tran<T>(self, data: &T) -> error::Result<Self>
+        where T: Serialize + ?Sized
+    {
+        self.tran_with(&Config::default(), data)
+    }
+
+    /// set the body to the envelope of data encoded with config, the content type to
+    /// [Config::media_type], and the Accept header to envelopes or plain JSON
+    fn tran_with<T>(self, config: &Config, data: &T) -> error::Result<Self>
+        where T: Serialize + ?Sized;
+}
+
+impl RequestBuilderExt for RequestBuilder {
+    fn tran_with<T>(self, config: &Config, data: &T) -> error::Result<Self>
+        where T: Serialize + ?Sized
+    {
+        let body = config.to_json(data)?.to_vec()?;
+        let accept = format!("{}, {};q=0.9, application/json;q=0.8", Binary::MEDIA_TYPE, Json::MEDIA_TYPE);
+
+        Ok(self
+            .header(CONTENT_TYPE, config.media_type().to_string())
+            .header(ACCEPT, accept)
+            .body(body))
+    }
+}
+
+/// [ResponseExt] receives data in [crate::Json] envelopes
+pub trait ResponseExt {
+    /// decode the body into T, see [ResponseExt::tran_with]
+    fn tran<T>(self) -> impl Future<Output = Result<T, ClientError>> + Send
+        where T: for<'de> Deserialize<'de>;
+
+    /// decode the body into T, with the [crate::Limits] in config.
+    ///
+    /// the body is a [crate::Json] envelope, a [Binary] envelope (by the content type), or plain
+    /// JSON. The `application/json` body is decoded as an envelope first (the servers which send
+    /// it with `HttpResponse::Ok().json(json)`), then as plain JSON (the servers which do not
+    /// support envelopes), whose errors have no [crate::Stage]. The responses which are not
+    /// successful (2xx) are returned as [ClientError::Status].
+    fn tran_with<T>(self, config: &Config) -> impl Future<Output = Result<T, ClientError>> + Send
+        where T: for<'de> Deserialize<'de>;
+}
+
+impl ResponseExt for Response {
+    async fn tran<T>(self) -> Result<T, ClientError>
+        where T: for<'de> Deserialize<'de>
+    {
+        self.tran_with(&Config::default()).await
+    }
+
+    async fn tran_with<T>(self, config: &Config) -> Result<T, ClientError>
+        where T: for<'de> Deserialize<'de>
+    {
+        let status = self.status();
+        if !status.is_success() {
+            let body = self.text().await.unwrap_or_default();
+            return Err(ClientError::Status { status, body });
+        }
+
+        let content_type = self.headers().get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let is_json = content_type.as_deref()
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("application/json"));
+        let body = self.bytes().await?;
+
+        if is_json {
+            if let Ok(json) = crate::from_json_slice(&body) {
+                return Ok(json.to_value_with(config)?);
+            }
+
+            // plain JSON is not in the pipeline of the envelopes, so the errors have no stage
+            config.limits.check_decoded_length(body.len() as u64)?;
+            return Ok(serde_json::from_slice(&body).map_err(error::Error::from)?);
+        }
+
+        Ok(config.from_body(content_type.as_deref(), &body)?)
+    }
+}
+
+/// [ClientError] is the error of [ResponseExt]
+#[derive(Debug)]
+pub enum ClientError {
+    /// sending the request or reading the response failed
+    Http(::reqwest::Error),
+    /// the status code of the response is not successful (2xx), with the body in text
+    Status {
+        status: StatusCode,
+        body: String,
+    },
+    /// decoding the body failed
+    Tran(error::Error),
+}
+
+impl From<::reqwest::Error> for ClientError {
+    #[inline]
+    fn from(err: ::reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+impl From<error::Error> for ClientError {
+    #[inline]
+    fn from(err: error::Error) -> Self {
+        Self::Tran(err)
+    }
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http(err) => Display::fmt(err, f),
+            Self::Status { status, body } => write!(f, "status {}: {}", status, body),
+            Self::Tran(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(err) => Some(err),
+            Self::Status { .. } => None,
+            Self::Tran(err) => Some(err),
+        }
+    }
+}
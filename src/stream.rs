@@ -0,0 +1,319 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use crate::{Base, Config, error};
+use crate::error::{ResultExt, Stage};
+
+impl Config {
+    /// write data as a [crate::Json] document into the writer, see [write_json_envelope]
+    pub fn write_json_envelope<W, T>(&self, writer: &mut W, data: &T) -> error::Result<()>
+        where
+            W: Write + ?Sized,
+            T: Serialize + ?Sized,
+    {
+        writer.write_all(br#"{"f":"#).at(Stage::Encode)?;
+        serde_json::to_writer(&mut *writer, &Base::Base64).at(Stage::Encode)?;
+        writer.write_all(br#","v":""#).at(Stage::Encode)?;
+
+        {
+            let mut encoder = base64::write::EncoderWriter::new(&mut *writer, &BASE64_URL_SAFE_NO_PAD);
+            self.to_writer(&mut encoder, data)?;
+            encoder.finish().at(Stage::Encode)?;
+        }
+
+        writer.write_all(br#""}"#).at(Stage::Encode)?;
+        Ok(())
+    }
+
+    /// read a [crate::Json] document from the reader, and convert it back to T,
+    /// see [read_json_envelope].
+    ///
+    /// [crate::Limits] apply to the decoded payload.
+    pub fn read_json_envelope<R, T>(&self, reader: R) -> error::Result<T>
+        where
+            R: Read,
+            T: for<'de> Deserialize<'de>,
+    {
+        let mut reader = EnvelopeReader::new(reader);
+
+        let mut format: Option<Base> = None;
+        let mut buffered: Option<String> = None;
+        let mut value: Option<T> = None;
+
+        reader.expect(b'{')?;
+        if reader.peek_token()? == b'}' {
+            reader.next_byte()?;
+        } else {
+            loop {
+                reader.expect(b'"')?;
+                let key = reader.read_string()?;
+                reader.expect(b':')?;
+
+                match key.as_str() {
+                    "f" => {
+                        reader.expect(b'"')?;
+                        let name = reader.read_string()?;
+                        format = Some(serde_json::from_value(serde_json::Value::String(name)).at(Stage::EnvelopeParse)?);
+                    },
+                    "v" => {
+                        reader.expect(b'"')?;
+                        match format {
+                            Some(Base::Base64) => {
+                                let decoder = base64::read::DecoderReader::new(
+                                    StringReader { reader: &mut reader, finished: false },
+                                    &BASE64_URL_SAFE_NO_PAD,
+                                );
+                                // io errors of the decoder are invalid base64 strings
+                                value = Some(self.from_reader(decoder).at(Stage::BaseDecode)?);
+                            },
+                            _ => buffered = Some(reader.read_string()?),
+                        }
+                    },
+                    _ => reader.skip_value()?,
+                }
+
+                match reader.next_token()? {
+                    b',' => continue,
+                    b'}' => break,
+                    other => return Err(syntax_error(format!("expected `,` or `}}`, found `{}`", other as char))),
+                }
+            }
+        }
+
+        reader.expect_end()?;
+
+        if let Some(value) = value {
+            return Ok(value);
+        }
+
+        match (format, buffered) {
+            (Some(format), Some(data)) => self.from_base(&data, format),
+            (None, _) => Err(syntax_error("missing field `f`")),
+            (_, None) => Err(syntax_error("missing field `v`")),
+        }
+    }
+}
+
+/// write data as a [crate::Json] document (with format [Base::Base64]) into the writer.
+///
+/// the output is the same as `to_json_base64(data)?.to_vec()?`, but the base64 string
+/// is encoded incrementally into the writer instead of being built in memory.
+pub fn write_json_envelope<W, T>(writer: &mut W, data: &T) -> error::Result<()>
+    where
+        W: Write + ?Sized,
+        T: Serialize + ?Sized,
+{
+    Config::default().write_json_envelope(writer, data)
+}
+
+/// read a [crate::Json] document from the reader, and convert it back to T.
+///
+/// when the `f` field comes before `v` (which is what [write_json_envelope] and
+/// [crate::Json::to_vec] write) and the format is [Base::Base64], the `v` string is
+/// decoded while it is read, so the base64 string is never held in memory.
+pub fn read_json_envelope<R, T>(reader: R) -> error::Result<T>
+    where
+        R: Read,
+        T: for<'de> Deserialize<'de>,
+{
+    Config::default().read_json_envelope(reader)
+}
+
+#[inline]
+fn syntax_error<T: std::fmt::Display>(msg: T) -> error::Error {
+    error::Error::from(<serde_json::Error as serde::de::Error>::custom(msg)).at(Stage::EnvelopeParse)
+}
+
+/// [EnvelopeReader] is a minimal pull parser, which only understands the envelope object.
+struct EnvelopeReader<R> {
+    inner: BufReader<R>,
+}
+
+impl<R: Read> EnvelopeReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            inner: BufReader::new(reader),
+        }
+    }
+
+    fn peek_byte(&mut self) -> error::Result<Option<u8>> {
+        Ok(self.inner.fill_buf().at(Stage::EnvelopeParse)?.first().copied())
+    }
+
+    fn next_byte(&mut self) -> error::Result<u8> {
+        let byte = self.peek_byte()?.ok_or_else(|| syntax_error("unexpected end of envelope"))?;
+        self.inner.consume(1);
+        Ok(byte)
+    }
+
+    /// skip whitespaces, and return the next byte without consuming it
+    fn peek_token(&mut self) -> error::Result<u8> {
+        while let Some(byte) = self.peek_byte()? {
+            if !byte.is_ascii_whitespace() {
+                return Ok(byte);
+            }
+            self.inner.consume(1);
+        }
+
+        Err(syntax_error("unexpected end of envelope"))
+    }
+
+    fn next_token(&mut self) -> error::Result<u8> {
+        self.peek_token()?;
+        self.next_byte()
+    }
+
+    /// only whitespaces may follow the envelope
+    fn expect_end(&mut self) -> error::Result<()> {
+        while let Some(byte) = self.peek_byte()? {
+            if !byte.is_ascii_whitespace() {
+                return Err(syntax_error(format!("trailing characters after the envelope, found `{}`", byte as char)));
+            }
+            self.inner.consume(1);
+        }
+
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: u8) -> error::Result<()> {
+        match self.next_token()? {
+            byte if byte == expected => Ok(()),
+            byte => Err(syntax_error(format!("expected `{}`, found `{}`", expected as char, byte as char))),
+        }
+    }
+
+    /// read the next char of a string (the opening quote is consumed), None for the closing quote
+    fn next_char(&mut self) -> error::Result<Option<char>> {
+        match self.next_byte()? {
+            b'"' => Ok(None),
+            b'\\' => {
+                let c = match self.next_byte()? {
+                    b'"' => '"',
+                    b'\\' => '\\',
+                    b'/' => '/',
+                    b'b' => '\u{8}',
+                    b'f' => '\u{c}',
+                    b'n' => '\n',
+                    b'r' => '\r',
+                    b't' => '\t',
+                    b'u' => match self.read_hex4()? {
+                        high @ 0xd800..=0xdbff => {
+                            // a surrogate pair, the low surrogate must follow
+                            if self.next_byte()? != b'\\' || self.next_byte()? != b'u' {
+                                return Err(syntax_error("lone surrogate in unicode escape"));
+                            }
+                            match self.read_hex4()? {
+                                low @ 0xdc00..=0xdfff => {
+                                    let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                                    char::from_u32(code).ok_or_else(|| syntax_error("invalid unicode escape"))?
+                                },
+                                _ => return Err(syntax_error("lone surrogate in unicode escape")),
+                            }
+                        },
+                        code => char::from_u32(code).ok_or_else(|| syntax_error("lone surrogate in unicode escape"))?,
+                    },
+                    other => return Err(syntax_error(format!("invalid escape `\\{}`", other as char))),
+                };
+                Ok(Some(c))
+            },
+            byte if byte.is_ascii() => Ok(Some(byte as char)),
+            byte => {
+                // a multi-byte utf-8 char, collect the continuation bytes
+                let width = match byte {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    _ => 4,
+                };
+                let mut buf = [byte, 0, 0, 0];
+                for b in buf.iter_mut().take(width).skip(1) {
+                    *b = self.next_byte()?;
+                }
+                let s = std::str::from_utf8(&buf[..width]).map_err(syntax_error)?;
+                Ok(s.chars().next())
+            },
+        }
+    }
+
+    /// read the 4 hex digits of a `\u` escape
+    fn read_hex4(&mut self) -> error::Result<u32> {
+        let mut code = 0u32;
+        for _ in 0..4 {
+            let digit = (self.next_byte()? as char).to_digit(16)
+                .ok_or_else(|| syntax_error("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn read_string(&mut self) -> error::Result<String> {
+        let mut s = String::new();
+        while let Some(c) = self.next_char()? {
+            s.push(c);
+        }
+
+        Ok(s)
+    }
+
+    /// skip a json value of an unknown field
+    fn skip_value(&mut self) -> error::Result<()> {
+        match self.next_token()? {
+            b'"' => {
+                while self.next_char()?.is_some() {}
+            },
+            b'{' | b'[' => {
+                let mut depth = 1usize;
+                while depth > 0 {
+                    match self.next_token()? {
+                        b'"' => while self.next_char()?.is_some() {},
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {},
+                    }
+                }
+            },
+            _ => {
+                // number, true, false or null
+                while let Some(byte) = self.peek_byte()? {
+                    if matches!(byte, b',' | b'}' | b']') || byte.is_ascii_whitespace() {
+                        break;
+                    }
+                    self.inner.consume(1);
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// [StringReader] reads the raw content of a json string, until the closing quote.
+struct StringReader<'a, R> {
+    reader: &'a mut EnvelopeReader<R>,
+    finished: bool,
+}
+
+impl<R: Read> Read for StringReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut n = 0;
+
+        while n < buf.len() && !self.finished {
+            let c = self.reader.next_char()
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+            match c {
+                Some(c) if c.is_ascii() => {
+                    buf[n] = c as u8;
+                    n += 1;
+                },
+                Some(c) => return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid base64 char `{}`", c),
+                )),
+                None => self.finished = true,
+            }
+        }
+
+        Ok(n)
+    }
+}
@@ -24,7 +24,6 @@ mod tests {
                 c: rng.gen(),
                 d: rng.gen(),
                 e: (0..rng.gen_range(4..32))
-                    .into_iter()
                     .map(|idx| {
                         (
                             idx,
@@ -142,4 +141,1784 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn to_writer_then_from_reader() -> anyhow::Result<()> {
+        const BATCH_SIZE: usize = 128;
+
+        for _ in 0..BATCH_SIZE {
+            let origin = A::rand();
+
+            let mut bytes = Vec::new();
+            crate::to_writer(&mut bytes, &origin)?;
+
+            #[cfg(not(feature = "flate2"))]
+            assert_eq!(bytes, crate::to_vec(&origin)?);
+
+            let parsed: A = crate::from_slice(&bytes)?;
+            assert_eq!(origin, parsed);
+
+            let parsed: A = crate::from_reader(bytes.as_slice())?;
+            assert_eq!(origin, parsed);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "serde_json", feature = "base64"))]
+    #[test]
+    fn write_json_envelope_then_read_json_envelope() -> anyhow::Result<()> {
+        const BATCH_SIZE: usize = 128;
+
+        for _ in 0..BATCH_SIZE {
+            let origin = A::rand();
+
+            let mut bytes = Vec::new();
+            crate::write_json_envelope(&mut bytes, &origin)?;
+
+            #[cfg(not(feature = "flate2"))]
+            assert_eq!(bytes, crate::to_json_base64(&origin)?.to_vec()?);
+
+            let parsed: A = crate::from_json_slice(&bytes)?.to_value()?;
+            assert_eq!(origin, parsed);
+
+            let parsed: A = crate::read_json_envelope(bytes.as_slice())?;
+            assert_eq!(origin, parsed);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "serde_json", feature = "base64"))]
+    #[test]
+    fn read_json_envelope_with_unordered_fields() -> anyhow::Result<()> {
+        let origin = A::rand();
+        let json = crate::to_json_base64(&origin)?;
+
+        let document = format!(
+            r#" {{ "extra": {{"a": [1, "}}", null]}}, "v" : "{}", "n": -1.5e3, "f": "base64" }} "#,
+            json.data(),
+        );
+        let parsed: A = crate::read_json_envelope(document.as_bytes())?;
+        assert_eq!(origin, parsed);
+
+        let document = format!(r#"{{"f":"base64","v":"{}"}}"#, json.data().replace('_', "\\u005f"));
+        let parsed: A = crate::read_json_envelope(document.as_bytes())?;
+        assert_eq!(origin, parsed);
+
+        assert!(crate::read_json_envelope::<_, A>(r#"{"f":"base64"}"#.as_bytes()).is_err());
+        assert!(crate::read_json_envelope::<_, A>(r#"{"f":"base64","v":"AAAA""#.as_bytes()).is_err());
+
+        // only whitespaces may follow the envelope, like from_json_slice
+        let document = format!(r#"{{"f":"base64","v":"{}"}} trailing"#, json.data());
+        assert!(crate::from_json_slice(&document).is_err());
+        let err = crate::read_json_envelope::<_, A>(document.as_bytes()).unwrap_err();
+        assert_eq!(err.stage(), Some(crate::Stage::EnvelopeParse));
+        let document = format!(r#"{{"v":"{}","f":"base64"}}}}"#, json.data());
+        let err = crate::read_json_envelope::<_, A>(document.as_bytes()).unwrap_err();
+        assert_eq!(err.stage(), Some(crate::Stage::EnvelopeParse));
+
+        // surrogate pairs are decoded, lone surrogates are rejected
+        let document = format!(r#"{{"x":"\ud83d\ude00","f":"base64","v":"{}"}}"#, json.data());
+        let parsed: A = crate::read_json_envelope(document.as_bytes())?;
+        assert_eq!(origin, parsed);
+        for lone in [r#"\ud83d"#, r#"\ud83d\u0041"#, r#"\ude00"#] {
+            let document = format!(r#"{{"x":"{}","f":"base64","v":"{}"}}"#, lone, json.data());
+            let err = crate::read_json_envelope::<_, A>(document.as_bytes()).unwrap_err();
+            assert_eq!(err.stage(), Some(crate::Stage::EnvelopeParse), "{}", lone);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_with_legacy_payload() -> anyhow::Result<()> {
+        use std::hash::Hasher;
+
+        let origin = A::rand();
+
+        // payloads before the codec header: bincode serialized (data, hash)
+        let data = bincode::serialize(&origin)?;
+        let mut hasher = std::hash::DefaultHasher::new();
+        hasher.write(&data);
+        let hash = if cfg!(feature = "no-hash-validate") { 0 } else { hasher.finish() };
+        #[allow(unused_mut)]
+        let mut bytes = bincode::serialize(&(data, hash))?;
+
+        #[cfg(feature = "flate2")]
+        {
+            use std::io::Write;
+            let mut e = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            e.write_all(&bytes)?;
+            bytes = e.finish()?;
+        }
+
+        let parsed: A = crate::from_slice(&bytes)?;
+        assert_eq!(origin, parsed);
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "no-hash-validate"))]
+    #[test]
+    fn from_slice_with_broken_payload() -> anyhow::Result<()> {
+        let origin = A::rand();
+        let bytes = crate::Config::new().to_vec(&origin)?;
+
+        #[cfg(not(feature = "flate2"))]
+        {
+            let mut broken = bytes.clone();
+            let last = broken.len() - 9;
+            broken[last] ^= 0xff;
+            assert!(matches!(crate::from_slice::<A>(&broken).map_err(crate::Error::into_kind), Err(crate::ErrorKind::HashError(..))));
+        }
+
+        assert!(crate::from_slice::<A>(&bytes[..bytes.len() - 1]).is_err());
+
+        Ok(())
+    }
+
+    fn to_vec_then_from_slice_with_codec(codec: crate::CodecId) -> anyhow::Result<()> {
+        const BATCH_SIZE: usize = 32;
+
+        let config = crate::Config::new().codec(codec);
+        for _ in 0..BATCH_SIZE {
+            let origin = A::rand();
+
+            let bytes = config.to_vec(&origin)?;
+            let parsed: A = crate::from_slice(&bytes)?;
+
+            assert_eq!(origin, parsed);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_vec_then_from_slice_with_codecs() -> anyhow::Result<()> {
+        to_vec_then_from_slice_with_codec(crate::CodecId::Bincode)?;
+        #[cfg(feature = "rmp-serde")]
+        to_vec_then_from_slice_with_codec(crate::CodecId::MessagePack)?;
+        #[cfg(feature = "postcard")]
+        to_vec_then_from_slice_with_codec(crate::CodecId::Postcard)?;
+        #[cfg(feature = "bitcode")]
+        to_vec_then_from_slice_with_codec(crate::CodecId::Bitcode)?;
+        #[cfg(feature = "ciborium")]
+        to_vec_then_from_slice_with_codec(crate::CodecId::Cbor)?;
+
+        #[cfg(not(feature = "postcard"))]
+        assert!(crate::Config::new().codec(crate::CodecId::Postcard).to_vec(&A::rand()).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "serde_json", any(feature = "rmp-serde", feature = "ciborium")))]
+    #[test]
+    fn to_json_then_from_json_with_self_describing_codec() -> anyhow::Result<()> {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        #[serde(untagged)]
+        enum Untagged {
+            Number(i64),
+            Text { text: String },
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        #[serde(tag = "type")]
+        enum Tagged {
+            Login { username: String },
+            Logout,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Inner {
+            x: u32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            y: Option<String>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Dynamic {
+            value: serde_json::Value,
+            untagged: Vec<Untagged>,
+            tagged: Vec<Tagged>,
+            #[serde(flatten)]
+            inner: Inner,
+            a: A,
+        }
+
+        let origin = Dynamic {
+            value: serde_json::json!({"a": [1, 2.5, null, true], "b": {"c": "d"}}),
+            untagged: vec![Untagged::Number(-3), Untagged::Text { text: "hello".to_string() }],
+            tagged: vec![Tagged::Login { username: "serde_tran".to_string() }, Tagged::Logout],
+            inner: Inner { x: 7, y: None },
+            a: A::rand(),
+        };
+
+        // bincode can not round-trip these types
+        assert!(crate::to_json(&origin).and_then(|json| json.to_value::<Dynamic>()).is_err());
+
+        let codecs = [
+            #[cfg(feature = "rmp-serde")]
+            crate::CodecId::MessagePack,
+            #[cfg(feature = "ciborium")]
+            crate::CodecId::Cbor,
+        ];
+
+        for codec in codecs {
+            let config = crate::Config::new().codec(codec);
+            let bytes = config.to_json(&origin)?.to_vec()?;
+            let parsed: Dynamic = crate::from_json_slice(&bytes)?.to_value()?;
+            assert_eq!(origin, parsed);
+
+            let bytes = config.to_vec(&origin)?;
+            let parsed: Dynamic = crate::from_slice(&bytes)?;
+            assert_eq!(origin, parsed);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_vec_then_from_slice_with_bincode_options() -> anyhow::Result<()> {
+        use crate::{Bincode, Endian, IntEncoding};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Small {
+            id: u64,
+            name: String,
+            tags: Vec<u32>,
+        }
+
+        let origin = Small { id: 7, name: "serde_tran".to_string(), tags: vec![1, 2, 3] };
+
+        let mut sizes = Vec::new();
+        for int_encoding in [IntEncoding::Fixint, IntEncoding::Varint] {
+            for endian in [Endian::Little, Endian::Big] {
+                let bincode = Bincode::new().int_encoding(int_encoding).endian(endian);
+                let config = crate::Config::new().bincode(bincode);
+
+                let bytes = config.to_vec(&origin)?;
+                let parsed: Small = crate::from_slice(&bytes)?;
+                assert_eq!(origin, parsed);
+
+                let a = A::rand();
+                let parsed: A = crate::from_slice(&config.to_vec(&a)?)?;
+                assert_eq!(a, parsed);
+
+                sizes.push((int_encoding, bytes.len()));
+            }
+        }
+
+        #[cfg(not(feature = "flate2"))]
+        {
+            let fixint = sizes.iter().find(|(e, _)| *e == IntEncoding::Fixint).unwrap().1;
+            let varint = sizes.iter().find(|(e, _)| *e == IntEncoding::Varint).unwrap().1;
+            assert!(varint < fixint);
+        }
+
+        // the default options produce the layout of 1.x, the data of bincode::serialize with its hash,
+        // so the peers of 1.x can decode it
+        let bytes = crate::to_vec(&origin)?;
+        #[cfg(not(feature = "flate2"))]
+        {
+            assert_eq!(&bytes[8..bytes.len() - 8], bincode::serialize(&origin)?.as_slice());
+            let (data, _hash): (Vec<u8>, u64) = bincode::deserialize(&bytes)?;
+            assert_eq!(origin, bincode::deserialize::<Small>(&data)?);
+        }
+        let parsed: Small = crate::from_slice(&bytes)?;
+        assert_eq!(origin, parsed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_vec_verified_with_asymmetric_attributes() -> anyhow::Result<()> {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct SkipIf {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            a: Option<u32>,
+            b: u32,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        #[serde(untagged)]
+        enum Untagged {
+            Number(u32),
+            Text(String),
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Skip {
+            a: u32,
+            #[serde(skip)]
+            b: u32,
+        }
+
+        let origin = A::rand();
+        let bytes = crate::to_vec_verified(&origin)?;
+        assert_eq!(origin, crate::from_slice::<A>(&bytes)?);
+        let bytes = crate::to_vec_verified_eq(&origin)?;
+        assert_eq!(origin, crate::from_slice::<A>(&bytes)?);
+
+        let err = crate::to_vec_verified(&SkipIf { a: None, b: 5 }).unwrap_err();
+        assert!(matches!(err.kind(), crate::ErrorKind::VerifyError(_)));
+        assert_eq!(err.stage(), Some(crate::Stage::Verify));
+        assert!(err.to_string().contains("skip_serializing_if"), "{}", err);
+        // the attribute is fine when the field is serialized
+        crate::to_vec_verified(&SkipIf { a: Some(1), b: 5 })?;
+
+        let err = crate::to_vec_verified(&Untagged::Number(1)).unwrap_err();
+        assert!(err.to_string().contains("untagged"), "{}", err);
+        assert!(err.to_string().contains("Untagged"), "{}", err);
+
+        crate::to_vec_verified(&Skip { a: 1, b: 2 })?;
+        let err = crate::to_vec_verified_eq(&Skip { a: 1, b: 2 }).unwrap_err();
+        assert!(err.to_string().contains("#[serde(skip)]"), "{}", err);
+
+        let config = crate::Config::new().verify(crate::Verify::Never);
+        config.to_vec_verified(&SkipIf { a: None, b: 5 })?;
+
+        let config = crate::Config::new().verify(crate::Verify::DebugOnly);
+        assert_eq!(config.to_vec_verified_eq(&Skip { a: 1, b: 2 }).is_err(), cfg!(debug_assertions));
+
+        // the nonce of the payload is kept when verifying, and it is not recorded
+        let config = crate::Config::new().nonce(true)
+            .replay_protection(std::time::Duration::from_secs(60), crate::MemoryNonceStore::new());
+        let bytes = config.to_vec_verified(&origin)?;
+        assert_eq!(origin, config.from_slice::<A>(&bytes)?);
+        let bytes = config.to_vec_verified_eq(&origin)?;
+        assert_eq!(origin, config.from_slice::<A>(&bytes)?);
+
+        // the limits of the config apply when verifying
+        let config = crate::Config::new().limits(crate::Limits::new().depth(1));
+        let err = config.to_vec_verified(&vec![vec![1u8]]).unwrap_err();
+        assert!(err.to_string().contains("nesting depth"), "{}", err);
+
+        #[cfg(feature = "serde_json")]
+        {
+            let json = crate::to_json_verified(&origin)?;
+            assert_eq!(origin, json.to_value::<A>()?);
+            assert!(crate::to_json_verified_eq(&Skip { a: 1, b: 2 }).is_err());
+        }
+
+        #[cfg(feature = "rmp-serde")]
+        {
+            let config = crate::Config::new().codec(crate::CodecId::MessagePack);
+            config.to_vec_verified_eq(&SkipIf { a: None, b: 5 })?;
+            config.to_vec_verified_eq(&Untagged::Text("hello".to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_with_limits() -> anyhow::Result<()> {
+        use crate::{Config, ErrorKind, Limit, Limits};
+
+        let origin = vec![7u8; 1024];
+        let bytes = crate::to_vec(&origin)?;
+
+        let config = Config::new().limits(Limits::new().decoded_length(16));
+        assert!(matches!(config.from_slice::<Vec<u8>>(&bytes).map_err(crate::Error::into_kind), Err(ErrorKind::LimitExceeded(Limit::DecodedLength(16)))));
+        assert!(matches!(config.from_reader::<_, Vec<u8>>(bytes.as_slice()).map_err(crate::Error::into_kind), Err(ErrorKind::LimitExceeded(Limit::DecodedLength(16)))));
+        #[cfg(feature = "base64")]
+        {
+            let s = crate::to_base64(&origin)?;
+            assert!(matches!(config.from_base64::<Vec<u8>>(&s).map_err(crate::Error::into_kind), Err(ErrorKind::LimitExceeded(Limit::DecodedLength(16)))));
+        }
+        #[cfg(feature = "bs58")]
+        {
+            let s = crate::to_base58(&origin)?;
+            assert!(matches!(config.from_base58::<Vec<u8>>(&s).map_err(crate::Error::into_kind), Err(ErrorKind::LimitExceeded(Limit::DecodedLength(16)))));
+        }
+        #[cfg(all(feature = "serde_json", feature = "base64"))]
+        {
+            let json = crate::to_json(&origin)?;
+            assert!(json.to_value_with::<Vec<u8>>(&config).is_err());
+
+            let mut envelope = Vec::new();
+            crate::write_json_envelope(&mut envelope, &origin)?;
+            assert!(matches!(config.read_json_envelope::<_, Vec<u8>>(envelope.as_slice()).map_err(crate::Error::into_kind), Err(ErrorKind::LimitExceeded(Limit::DecodedLength(16)))));
+        }
+
+        let config = Config::new().limits(Limits::new().decompressed_size(16));
+        assert!(matches!(config.from_slice::<Vec<u8>>(&bytes).map_err(crate::Error::into_kind), Err(ErrorKind::LimitExceeded(Limit::DecompressedSize(16)))));
+
+        let config = Config::new().limits(Limits::new().allocation(16));
+        let result = config.from_slice::<Vec<u8>>(&bytes);
+        assert!(matches!(result.as_ref().map_err(crate::Error::kind), Err(ErrorKind::LimitExceeded(Limit::Allocation(16)))), "{:?}", result);
+
+        // the limits are large enough
+        let config = Config::new().limits(Limits::new().decoded_length(4096).decompressed_size(4096).allocation(4096).depth(1));
+        assert_eq!(origin, config.from_slice::<Vec<u8>>(&bytes)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_with_depth_limit() -> anyhow::Result<()> {
+        use crate::{CodecId, Config, ErrorKind, Limit, Limits};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        enum Tree {
+            Leaf,
+            Node(Box<Tree>),
+        }
+
+        let mut origin = Tree::Leaf;
+        for _ in 0..8 {
+            origin = Tree::Node(Box::new(origin));
+        }
+
+        #[allow(unused_mut)]
+        let mut codecs = vec![CodecId::Bincode];
+        #[cfg(feature = "rmp-serde")]
+        codecs.push(CodecId::MessagePack);
+        #[cfg(feature = "postcard")]
+        codecs.push(CodecId::Postcard);
+        #[cfg(feature = "ciborium")]
+        codecs.push(CodecId::Cbor);
+
+        for codec in codecs {
+            let bytes = Config::new().codec(codec).to_vec(&origin)?;
+
+            let config = Config::new().limits(Limits::new().depth(4));
+            let result = config.from_slice::<Tree>(&bytes);
+            assert!(matches!(result.as_ref().map_err(crate::Error::kind), Err(ErrorKind::LimitExceeded(Limit::Depth(4)))), "{:?}: {:?}", codec, result);
+
+            let config = Config::new().limits(Limits::new().depth(9));
+            assert_eq!(origin, config.from_slice::<Tree>(&bytes)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_stage_and_code() -> anyhow::Result<()> {
+        use std::error::Error as _;
+        use std::hash::Hasher;
+        use crate::{ErrorKind, Stage};
+
+        // header with a codec id, and the hash of codec byte and data
+        fn payload(codec: u8, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+            let mut hasher = std::hash::DefaultHasher::new();
+            hasher.write(&[codec]);
+            hasher.write(data);
+            let hash = if cfg!(feature = "no-hash-validate") { 0 } else { hasher.finish() };
+
+            #[allow(unused_mut)]
+            let mut bytes = [b"TRAN".as_slice(), &[1, codec], data, &hash.to_le_bytes()].concat();
+            #[cfg(feature = "flate2")]
+            {
+                use std::io::Write;
+                let mut e = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                e.write_all(&bytes)?;
+                bytes = e.finish()?;
+            }
+
+            Ok(bytes)
+        }
+
+        let err = crate::from_slice::<A>(&payload(0x0f, &[])?).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Header));
+        assert_eq!(err.code(), "custom");
+
+        #[cfg(not(feature = "rmp-serde"))]
+        {
+            let err = crate::from_slice::<A>(&payload(crate::CodecId::MessagePack.id(), &[0xc0])?).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::FeatureDisabled("rmp-serde")), "{}", err);
+            assert_eq!(err.stage(), Some(Stage::Header));
+            assert_eq!(err.code(), "feature_disabled");
+        }
+
+        let err = crate::from_slice::<A>(&payload(0, &[1, 2, 3])?).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::InnerDecode));
+        assert!(matches!(err.kind(), ErrorKind::BincodeError(_)));
+        assert!(err.source().is_some());
+
+        #[cfg(feature = "flate2")]
+        {
+            let err = crate::from_slice::<A>(b"not gzip").unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::Decompress));
+            assert_eq!(err.code(), "io");
+        }
+
+        #[cfg(all(not(feature = "flate2"), not(feature = "no-hash-validate")))]
+        {
+            let mut bytes = payload(0, &[1, 2, 3])?;
+            bytes[6] ^= 0xff;
+            let err = crate::from_slice::<A>(&bytes).unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::Checksum));
+            assert_eq!(err.code(), "hash_mismatch");
+            assert!(err.source().is_none());
+        }
+
+        #[cfg(feature = "serde_json")]
+        {
+            let err = crate::from_json_slice(b"{").unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::EnvelopeParse));
+            assert_eq!(err.code(), "json");
+            assert!(err.to_string().starts_with("envelope_parse: "), "{}", err);
+        }
+
+        #[cfg(all(feature = "serde_json", feature = "base64"))]
+        {
+            let err = crate::read_json_envelope::<_, A>(r#"{"f":"base64","v":"!!!!"}"#.as_bytes()).unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::BaseDecode));
+
+            let err = crate::read_json_envelope::<_, A>(r#"{"f":"base64"}"#.as_bytes()).unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::EnvelopeParse));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_with_path_of_failure() -> anyhow::Result<()> {
+        use crate::{CodecId, Config, Segment};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct OrdersV1 {
+            orders: Vec<OrderV1>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct OrderV1 {
+            id: u64,
+            items: Vec<(String, u8)>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct OrdersV2 {
+            orders: Vec<OrderV2>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct OrderV2 {
+            id: u64,
+            items: Vec<Item>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Item {
+            name: String,
+            price: bool,
+        }
+
+        let order = |n: u8| OrderV1 { id: n as u64, items: vec![("a".to_string(), 0), ("b".to_string(), n)] };
+        let origin = OrdersV1 { orders: vec![order(0), order(1), order(0), order(2)] };
+
+        #[allow(unused_mut)]
+        let mut codecs = vec![CodecId::Bincode];
+        #[cfg(feature = "rmp-serde")]
+        codecs.push(CodecId::MessagePack);
+        #[cfg(feature = "postcard")]
+        codecs.push(CodecId::Postcard);
+
+        for codec in codecs {
+            let bytes = Config::new().codec(codec).to_vec(&origin)?;
+            let err = crate::from_slice::<OrdersV2>(&bytes).unwrap_err();
+            let path = err.path().expect("path of the failure");
+
+            // integer 0 is decoded as false by the compact codecs, but not by MessagePack
+            let expected = match codec {
+                CodecId::MessagePack => "orders[0].items[0].price",
+                _ => "orders[3].items[1].price",
+            };
+            assert_eq!(path.to_string(), expected, "{:?}: {}", codec, err);
+            assert_eq!(path.segments()[0], Segment::Field("orders".into()));
+            assert!(err.to_string().contains(&format!("at `{}`", path)), "{}", err);
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        enum Event {
+            Created { id: u32 },
+            Deleted(u32, u32),
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        enum EventV2 {
+            Created { id: u32 },
+            Deleted(u32, bool),
+        }
+
+        let bytes = crate::to_vec(&vec![Event::Created { id: 1 }, Event::Deleted(1, 2)])?;
+        let err = crate::from_slice::<Vec<EventV2>>(&bytes).unwrap_err();
+        assert_eq!(err.path().map(|path| path.to_string()), Some("[1].Deleted[1]".to_string()), "{}", err);
+
+        // the failure is not in a field
+        let err = crate::from_slice::<bool>(&crate::to_vec(&2u8)?).unwrap_err();
+        assert!(err.path().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_vec_typed_then_from_slice() -> anyhow::Result<()> {
+        use crate::{CodecId, Config, ErrorKind, Stage};
+
+        mod server {
+            #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+            pub struct LoginRequest {
+                pub username: String,
+                pub password: String,
+            }
+        }
+
+        mod client {
+            #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+            pub struct LoginRequest {
+                pub username: String,
+                pub password: String,
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Comment {
+            author: String,
+            text: String,
+        }
+
+        let origin = client::LoginRequest { username: "user".to_string(), password: "pass".to_string() };
+
+        #[allow(unused_mut)]
+        let mut codecs = vec![CodecId::Bincode];
+        #[cfg(feature = "rmp-serde")]
+        codecs.push(CodecId::MessagePack);
+        #[cfg(feature = "ciborium")]
+        codecs.push(CodecId::Cbor);
+
+        for codec in codecs {
+            let config = Config::new().codec(codec);
+
+            // the same type in another module (or crate) has the same fingerprint
+            let bytes = config.to_vec_typed(&origin)?;
+            let parsed: server::LoginRequest = crate::from_slice(&bytes)?;
+            assert_eq!(origin.username, parsed.username);
+
+            // without the fingerprint, bincode decodes the bytes as any type with the same layout
+            let untyped = config.to_vec(&origin)?;
+            assert_eq!(codec == CodecId::Bincode, crate::from_slice::<Comment>(&untyped).is_ok());
+
+            let err = crate::from_slice::<Comment>(&bytes).unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::Header));
+            match err.kind() {
+                ErrorKind::TypeMismatch { expected, found } => {
+                    assert!(expected.ends_with("Comment"), "{}", expected);
+                    assert!(found.ends_with("client::LoginRequest"), "{}", found);
+                },
+                _ => panic!("unexpected error: {}", err),
+            }
+        }
+
+        #[cfg(feature = "serde_json")]
+        {
+            let json = crate::to_json_typed(&origin)?;
+            assert_eq!(origin, json.to_value::<client::LoginRequest>()?);
+            assert_eq!(json.to_value::<Comment>().unwrap_err().code(), "type_mismatch");
+        }
+
+        // the fingerprint is stable, it is part of the payload format
+        assert_eq!(crate::fingerprint::<u32>(), 0xc802_85e1_ad7b_c3c5);
+        assert_ne!(crate::fingerprint::<Vec<u32>>(), crate::fingerprint::<Vec<u64>>());
+        assert_ne!(crate::fingerprint::<Option<Comment>>(), crate::fingerprint::<Comment>());
+        assert_ne!(crate::fingerprint::<String>(), crate::fingerprint::<Vec<u8>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_versioned_with_migrations() -> anyhow::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use crate::{CodecId, Config, ErrorKind, Migrations, Stage, Versioned};
+
+        static BUILT: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct UserV0 {
+            name: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct UserV1 {
+            name: String,
+            age: u32,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct User {
+            name: String,
+            age: u32,
+            email: Option<String>,
+        }
+
+        impl Versioned for User {
+            const VERSION: u32 = 2;
+
+            fn migrations() -> Migrations<Self> {
+                BUILT.fetch_add(1, Ordering::SeqCst);
+                Migrations::new(0)
+                    .then(1, |v0: UserV0| UserV1 { name: v0.name, age: 18 })
+                    .then(2, |v1: UserV1| User { name: v1.name, age: v1.age, email: None })
+            }
+        }
+
+        let user = User { name: "user".to_string(), age: 30, email: Some("user@example.com".to_string()) };
+
+        #[allow(unused_mut)]
+        let mut codecs = vec![CodecId::Bincode];
+        #[cfg(feature = "rmp-serde")]
+        codecs.push(CodecId::MessagePack);
+        #[cfg(feature = "postcard")]
+        codecs.push(CodecId::Postcard);
+        #[cfg(feature = "ciborium")]
+        codecs.push(CodecId::Cbor);
+
+        for codec in codecs {
+            let config = Config::new().codec(codec);
+
+            let bytes = config.to_vec_versioned(&user)?;
+            assert_eq!(user, crate::from_slice_versioned::<User>(&bytes)?);
+            // the payloads with a schema version need the migrations
+            let err = crate::from_slice::<User>(&bytes).unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::Header));
+            assert!(matches!(err.kind(), ErrorKind::VersionedPayload(2)), "{}", err);
+            assert_eq!(err.code(), "versioned_payload");
+
+            let bytes = config.to_vec_versioned_as(1, &UserV1 { name: "v1".to_string(), age: 20 })?;
+            let expected = User { name: "v1".to_string(), age: 20, email: None };
+            assert_eq!(expected, crate::from_slice_versioned::<User>(&bytes)?);
+
+            // the payloads without a schema version are version 0
+            let bytes = config.to_vec(&UserV0 { name: "v0".to_string() })?;
+            let expected = User { name: "v0".to_string(), age: 18, email: None };
+            assert_eq!(expected, crate::from_slice_versioned::<User>(&bytes)?);
+
+            // a payload from the future
+            let bytes = config.to_vec_versioned_as(3, &user)?;
+            let err = crate::from_slice_versioned::<User>(&bytes).unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::Header));
+            assert!(matches!(err.kind(), ErrorKind::UnknownVersion(_, 3)), "{}", err);
+        }
+
+        // the fingerprint is checked against the type of each version
+        let bytes = crate::to_vec_typed(&UserV0 { name: "v0".to_string() })?;
+        assert_eq!("v0", crate::from_slice_versioned::<User>(&bytes)?.name);
+
+        #[cfg(feature = "serde_json")]
+        {
+            let json = crate::to_json(&UserV0 { name: "json".to_string() })?;
+            assert_eq!(18, json.to_value_versioned::<User>()?.age);
+
+            let json = crate::to_json_versioned(&user)?;
+            assert_eq!(user, json.to_value_versioned::<User>()?);
+        }
+
+        // the chain is built once
+        assert_eq!(1, BUILT.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+
+    #[test]
+    fn registry_decode_tagged() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+        use crate::{CodecId, Config, ErrorKind, Registry, Stage, Tagged};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Join {
+            room: String,
+        }
+
+        impl Tagged for Join {
+            const TAG: &'static str = "join";
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Say {
+            room: String,
+            text: String,
+        }
+
+        impl Tagged for Say {
+            const TAG: &'static str = "say";
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        enum Message {
+            Join(Join),
+            Say(Say),
+        }
+
+        let registry = Registry::new()
+            .register(Message::Join)
+            .register(Message::Say);
+        assert!(registry.contains("say"));
+
+        let join = Join { room: "lobby".to_string() };
+        let say = Say { room: "lobby".to_string(), text: "hello".to_string() };
+
+        #[allow(unused_mut)]
+        let mut codecs = vec![CodecId::Bincode];
+        #[cfg(feature = "rmp-serde")]
+        codecs.push(CodecId::MessagePack);
+        #[cfg(feature = "ciborium")]
+        codecs.push(CodecId::Cbor);
+
+        for codec in codecs {
+            let config = Config::new().codec(codec);
+
+            assert_eq!(Message::Join(join.clone()), registry.decode_slice(&config.to_vec_tagged(&join)?)?);
+            assert_eq!(Message::Say(say.clone()), registry.decode_slice(&config.to_vec_tagged(&say)?)?);
+            // the tag is ignored
+            assert_eq!(say, crate::from_slice::<Say>(&config.to_vec_tagged(&say)?)?);
+
+            let err = registry.decode_slice(&config.to_vec(&say)?).unwrap_err();
+            assert_eq!(err.stage(), Some(Stage::Header));
+            assert!(matches!(err.kind(), ErrorKind::MissingTag), "{}", err);
+            assert_eq!(err.code(), "missing_tag");
+        }
+
+        // handlers
+        let said = Arc::new(Mutex::new(Vec::new()));
+        let handlers = Registry::new()
+            .register({
+                let said = said.clone();
+                move |say: Say| said.lock().unwrap().push(say.text)
+            });
+        handlers.decode_slice(&crate::to_vec_tagged(&say)?)?;
+        assert_eq!(vec!["hello".to_string()], *said.lock().unwrap());
+
+        // a registry can be shared between threads
+        std::thread::scope(|scope| {
+            scope.spawn(|| handlers.decode_slice(&crate::to_vec_tagged(&say).unwrap()).unwrap());
+        });
+        assert_eq!(2, said.lock().unwrap().len());
+
+        let err = handlers.decode_slice(&crate::to_vec_tagged(&join)?).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnknownTag(tag) if tag == "join"), "{}", err);
+
+        #[cfg(feature = "serde_json")]
+        {
+            let json = crate::to_json_tagged(&join)?;
+            assert_eq!(Message::Join(join.clone()), registry.decode(&json)?);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "token")]
+    #[test]
+    fn to_token_then_from_token() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+        use std::time::{Duration, SystemTime};
+        use crate::{Config, ErrorKind, Stage};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct PasswordReset {
+            user_id: u64,
+            email: String,
+        }
+
+        let now = Arc::new(Mutex::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        let config = Config::new().secret("secret").clock({
+            let now = now.clone();
+            move || *now.lock().unwrap()
+        });
+
+        let reset = PasswordReset { user_id: 1, email: "user@example.com".to_string() };
+        let token = config.to_token(&reset, Duration::from_secs(600))?;
+        assert!(token.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'), "{}", token);
+        assert_eq!(reset, config.from_token::<PasswordReset>(&token)?);
+
+        // another secret
+        let err = Config::new().secret("another").from_token::<PasswordReset>(&token).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Checksum));
+        assert!(matches!(err.kind(), ErrorKind::MacMismatch), "{}", err);
+
+        // tampered
+        let mut bytes = crate::from_base(&token, crate::Base::Base64)?;
+        let i = bytes.len() / 2;
+        bytes[i] ^= 1;
+        let tampered = crate::to_base(&bytes, crate::Base::Base64)?;
+        assert!(matches!(config.from_token::<PasswordReset>(&tampered).unwrap_err().kind(), ErrorKind::MacMismatch));
+        assert!(matches!(config.from_token::<PasswordReset>("AAAA").unwrap_err().kind(), ErrorKind::MacMismatch));
+
+        // without secret
+        assert!(Config::new().to_token(&reset, Duration::from_secs(600)).is_err());
+
+        *now.lock().unwrap() += Duration::from_secs(599);
+        assert_eq!(reset, config.from_token::<PasswordReset>(&token)?);
+
+        *now.lock().unwrap() += Duration::from_secs(1);
+        let err = config.from_token::<PasswordReset>(&token).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Validate));
+        assert_eq!(err.code(), "expired");
+        match err.kind() {
+            ErrorKind::Expired(at) => assert_eq!(*at, SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_600)),
+            _ => panic!("unexpected error: {}", err),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_with_replay_protection() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::{Duration, SystemTime};
+        use crate::{Config, ErrorKind, MemoryNonceStore, Nonce, NonceStore, Registry, Stage, Tagged};
+
+        let now = Arc::new(Mutex::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        let clock = {
+            let now = now.clone();
+            move || *now.lock().unwrap()
+        };
+
+        let sender = Config::new().nonce(true).clock(clock.clone());
+        let receiver = Config::new().clock(clock).replay_protection(Duration::from_secs(60), MemoryNonceStore::new());
+
+        let first = sender.to_vec("first")?;
+        let second = sender.to_vec("first")?;
+        assert_ne!(first, second);
+
+        // the nonce is ignored without replay protection
+        assert_eq!("first", crate::from_slice::<String>(&first)?);
+        assert_eq!("first", crate::from_slice::<String>(&first)?);
+
+        assert_eq!("first", receiver.from_slice::<String>(&first)?);
+        assert_eq!("first", receiver.from_slice::<String>(&second)?);
+
+        let err = receiver.from_slice::<String>(&first).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Validate));
+        assert!(matches!(err.kind(), ErrorKind::Replayed), "{}", err);
+
+        // the nonce is forgotten when the payload fails to decode, so it can be retried
+        let typed = sender.to_vec_typed(&"typed".to_string())?;
+        let err = receiver.from_slice::<u32>(&typed).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::TypeMismatch { .. }), "{}", err);
+        assert_eq!("typed", receiver.from_slice::<String>(&typed)?);
+        assert!(matches!(receiver.from_slice::<String>(&typed).unwrap_err().kind(), ErrorKind::Replayed));
+
+        // the nonce is recorded before the handler runs, so the handler runs exactly once
+        #[derive(Serialize, Deserialize)]
+        struct Ping;
+
+        impl Tagged for Ping {
+            const TAG: &'static str = "ping";
+        }
+
+        let pings = Arc::new(AtomicUsize::new(0));
+        let registry = Registry::new()
+            .register({
+                let pings = pings.clone();
+                move |_: Ping| pings.fetch_add(1, Ordering::SeqCst)
+            });
+        let ping = sender.to_vec_tagged(&Ping)?;
+        registry.decode_slice_with(&ping, &receiver)?;
+        let err = registry.decode_slice_with(&ping, &receiver).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Replayed), "{}", err);
+        assert_eq!(1, pings.load(Ordering::SeqCst));
+
+        // without nonce
+        let err = receiver.from_slice::<String>(&crate::to_vec("plain")?).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Validate));
+        assert!(matches!(err.kind(), ErrorKind::MissingNonce), "{}", err);
+        assert_eq!(err.code(), "missing_nonce");
+
+        #[cfg(feature = "serde_json")]
+        {
+            let json = sender.to_json("json")?;
+            assert_eq!("json", json.to_value_with::<String>(&receiver)?);
+            assert_eq!("replayed", json.to_value_with::<String>(&receiver).unwrap_err().code());
+        }
+
+        let third = sender.to_vec("third")?;
+        *now.lock().unwrap() += Duration::from_secs(60);
+        let err = receiver.from_slice::<String>(&third).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Validate));
+        assert!(matches!(err.kind(), ErrorKind::Expired(_)), "{}", err);
+
+        // the expired nonces are removed
+        let store = MemoryNonceStore::new();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for i in 0..2048u64 {
+            let now = start + Duration::from_secs(i);
+            let nonce = Nonce::generate(now);
+            assert!(store.insert(&nonce, now, now + Duration::from_secs(10)));
+            assert!(!store.insert(&nonce, now, now + Duration::from_secs(10)));
+        }
+        assert!(store.len() < 2048, "{}", store.len());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn to_cookie_value_within_budget() -> anyhow::Result<()> {
+        use crate::{Config, ErrorKind, Stage};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Session {
+            user_id: u64,
+            roles: Vec<u32>,
+        }
+
+        let session = Session { user_id: 7, roles: (0..200).collect() };
+        let config = Config::new();
+
+        let value = config.to_cookie_value(&session, 4096)?;
+        assert_eq!(value, config.to_base64(&session)?);
+        assert!(value.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'), "{}", value);
+        assert_eq!(session, crate::from_base64::<Session>(&value)?);
+
+        // smaller options are tried when the default one does not fit
+        let default_size = value.len();
+        let value = config.to_query_param(&session, default_size - 1)?;
+        assert!(value.len() < default_size);
+        assert_eq!(session, crate::from_base64::<Session>(&value)?);
+
+        let err = config.to_cookie_value(&session, 16).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Encode));
+        match err.kind() {
+            ErrorKind::TooLarge { size, budget } => assert!(*size > 16 && *budget == 16),
+            _ => panic!("unexpected error: {}", err),
+        }
+
+        // split across cookies
+        let cookies = config.to_cookies("session", &session, 64, 32)?;
+        assert!(cookies.len() > 1);
+        assert!(cookies.iter().all(|(_, value)| value.len() <= 64));
+
+        let mut request = vec![("theme".to_string(), "dark".to_string())];
+        request.extend(cookies.iter().rev().cloned());
+        assert_eq!(session, crate::from_cookies::<_, _, _, Session>("session", request)?);
+
+        let missing = cookies.iter().filter(|(name, _)| name != "session.1").cloned();
+        assert!(crate::from_cookies::<_, _, _, Session>("session", missing).is_err());
+
+        assert!(matches!(config.to_cookies("session", &session, 64, 1).unwrap_err().kind(), ErrorKind::TooLarge { .. }));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn to_armored_then_from_armored() -> anyhow::Result<()> {
+        use std::str::FromStr;
+        use crate::{Armored, Config, ErrorKind, Limits, Stage};
+
+        let data = (0..100u32).map(|i| format!("line {}", i)).collect::<Vec<_>>();
+
+        let armored = crate::to_armored(&data)?
+            .header("Comment", "pasted\ninto a ticket")
+            .header("Version", "1");
+        let text = armored.to_string();
+
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!("-----BEGIN SERDE TRAN-----", lines[0]);
+        assert_eq!("Comment: pasted into a ticket", lines[1]);
+        assert_eq!("", lines[3]);
+        assert_eq!("-----END SERDE TRAN-----", lines[lines.len() - 1]);
+        assert!(lines[lines.len() - 2].starts_with('=') && lines[lines.len() - 2].len() == 5);
+        assert!(lines.iter().all(|line| line.len() <= 64));
+
+        assert_eq!(data, crate::from_armored::<Vec<String>>(&text)?);
+
+        // surrounding text, indentation and CRLF
+        let quoted = text.lines().map(|line| format!("    {}  \r\n", line)).collect::<String>();
+        let email = format!("Hello,\r\n\r\n{}\r\nregards\r\n", quoted);
+        let parsed = Armored::from_str(&email)?;
+        assert_eq!(armored, parsed);
+        assert_eq!(Some("1"), parsed.get_header("Version"));
+        assert_eq!(data, parsed.to_value::<Vec<String>>()?);
+
+        // a character is mistyped
+        let body = lines.len() - 3;
+        let mut mistyped = lines.clone();
+        let line = mistyped[body].replacen(|c: char| c.is_ascii_alphabetic(), "0", 1);
+        mistyped[body] = &line;
+        let err = crate::from_armored::<Vec<String>>(&mistyped.join("\n")).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::Checksum));
+        assert!(matches!(err.kind(), ErrorKind::HashError(..)), "{}", err);
+
+        let err = crate::from_armored::<Vec<String>>(&text[..text.len() - 10]).unwrap_err();
+        assert_eq!(err.stage(), Some(Stage::EnvelopeParse));
+
+        let config = Config::new().limits(Limits::new().decoded_length(64));
+        let err = config.from_armored::<Vec<String>>(&text).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::LimitExceeded(_)), "{}", err);
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "actix-web", feature = "serde_json"))]
+    #[actix_web::test]
+    async fn actix_web_tran() -> anyhow::Result<()> {
+        use actix_web::{test, web, App};
+        use actix_web::http::StatusCode;
+        use crate::actix::Tran;
+        use crate::{Config, Limits};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Login {
+            username: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Hello {
+            message: String,
+        }
+
+        async fn login(Tran(login): Tran<Login>) -> Tran<Hello> {
+            Tran(Hello { message: format!("hello, {}", login.username) })
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Config::new().limits(Limits::new().decoded_length(256)))
+                .route("/login", web::post().to(login))
+        ).await;
+
+        let body = crate::to_json(&Login { username: "user".to_string() })?.to_string()?;
+        let req = test::TestRequest::post().uri("/login").set_payload(body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(crate::MediaType::from_content_type(resp.headers().get("content-type").unwrap().to_str()?).is_some());
+        let json = crate::from_json_slice(test::read_body(resp).await)?;
+        assert_eq!(Hello { message: "hello, user".to_string() }, json.to_value()?);
+
+        // not an envelope
+        let req = test::TestRequest::post().uri("/login").set_payload(r#"{"username":"user"}"#).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+        // another type
+        let body = crate::to_json(&42u8)?.to_string()?;
+        let req = test::TestRequest::post().uri("/login").set_payload(body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        // the errors are responded in JSON, like the other integrations
+        assert_eq!("application/json", resp.headers().get("content-type").unwrap());
+        let body: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await)?;
+        assert_eq!("inner_decode", body["stage"]);
+
+        // hex of hashes, which is not compressible
+        let username = (0..128u64).map(|i| {
+            use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+            format!("{:016x}", BuildHasherDefault::<DefaultHasher>::default().hash_one(i))
+        }).collect();
+        let body = crate::to_json(&Login { username })?.to_string()?;
+        let req = test::TestRequest::post().uri("/login").set_payload(body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+        let body: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await)?;
+        assert_eq!("limit_exceeded", body["code"]);
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "actix-web", feature = "serde_json", feature = "rmp-serde"))]
+    #[actix_web::test]
+    async fn actix_web_transparent() -> anyhow::Result<()> {
+        use actix_web::{test, web, App, HttpResponse};
+        use actix_web::http::StatusCode;
+        use crate::actix::{TRAN_HEADER, Transparent};
+        use crate::{CodecId, Config};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Login {
+            username: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Hello {
+            message: String,
+        }
+
+        async fn login(login: web::Json<Login>) -> HttpResponse {
+            HttpResponse::Ok().json(Hello { message: format!("hello, {}", login.username) })
+        }
+
+        // not self-describing
+        assert!(Transparent::new(Config::new()).is_err());
+
+        let config = Config::new().codec(CodecId::MessagePack);
+        let app = test::init_service(
+            App::new()
+                .service(web::resource("/login").wrap(Transparent::new(config.clone())?).route(web::post().to(login)))
+        ).await;
+
+        // opted in
+        let body = config.to_json(&Login { username: "user".to_string() })?.to_string()?;
+        let req = test::TestRequest::post().uri("/login")
+            .insert_header((TRAN_HEADER, "1"))
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(resp.headers().contains_key(TRAN_HEADER));
+        let json = crate::from_json_slice(test::read_body(resp).await)?;
+        assert_eq!(Hello { message: "hello, user".to_string() }, json.to_value_with(&config)?);
+
+        // plain JSON is untouched
+        let req = test::TestRequest::post().uri("/login")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"username":"user"}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(!resp.headers().contains_key(TRAN_HEADER));
+        assert_eq!("accept, x-serde-tran", resp.headers().get("vary").unwrap());
+        let hello: Hello = serde_json::from_slice(&test::read_body(resp).await)?;
+        assert_eq!(Hello { message: "hello, user".to_string() }, hello);
+
+        // opted in, but not an envelope
+        let req = test::TestRequest::post().uri("/login")
+            .insert_header((TRAN_HEADER, "1"))
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"username":"user"}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+        // always wraps the responses, but plain JSON requests are still accepted
+        let app = test::init_service(
+            App::new()
+                .service(web::resource("/login").wrap(Transparent::new(config.clone())?.always()).route(web::post().to(login)))
+        ).await;
+        let req = test::TestRequest::post().uri("/login")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"username":"user"}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(resp.headers().contains_key(TRAN_HEADER));
+        assert_eq!("accept, x-serde-tran", resp.headers().get("vary").unwrap());
+        let json = crate::from_json_slice(test::read_body(resp).await)?;
+        assert_eq!(Hello { message: "hello, user".to_string() }, json.to_value_with(&config)?);
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "axum", feature = "serde_json"))]
+    #[tokio::test]
+    async fn axum_tran() -> anyhow::Result<()> {
+        use axum::{Extension, Router};
+        use axum::body::{Body, to_bytes};
+        use axum::http::{Request, StatusCode};
+        use axum::routing::post;
+        use tower::ServiceExt;
+        use crate::axum::Tran;
+        use crate::{Config, Limits};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Login {
+            username: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Hello {
+            message: String,
+        }
+
+        async fn login(Tran(login): Tran<Login>) -> Tran<Hello> {
+            Tran(Hello { message: format!("hello, {}", login.username) })
+        }
+
+        let app = Router::new()
+            .route("/login", post(login))
+            .layer(Extension(Config::new().limits(Limits::new().decoded_length(256))));
+        let call = |body: String| {
+            let app = app.clone();
+            async move {
+                let req = Request::post("/login").body(Body::from(body))?;
+                let resp = app.oneshot(req).await?;
+                let status = resp.status();
+                let content_type = resp.headers().get("content-type").cloned();
+                let body = to_bytes(resp.into_body(), usize::MAX).await?;
+                anyhow::Ok((status, content_type, body))
+            }
+        };
+
+        let body = crate::to_json(&Login { username: "user".to_string() })?.to_string()?;
+        let (status, content_type, body) = call(body).await?;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(Config::default().media_type().to_string(), content_type.unwrap());
+        assert_eq!(Hello { message: "hello, user".to_string() }, crate::from_json_slice(&body)?.to_value()?);
+
+        // not an envelope
+        let (status, _, body) = call(r#"{"username":"user"}"#.to_string()).await?;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!("envelope_parse", body["stage"]);
+
+        // another type
+        let body = crate::to_json(&42u8)?.to_string()?;
+        let (status, _, body) = call(body).await?;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!("inner_decode", body["stage"]);
+
+        // hex of hashes, which is not compressible
+        let username = (0..128u64).map(|i| {
+            use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+            format!("{:016x}", BuildHasherDefault::<DefaultHasher>::default().hash_one(i))
+        }).collect();
+        let body = crate::to_json(&Login { username })?.to_string()?;
+        let (status, _, body) = call(body).await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status);
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!("limit_exceeded", body["code"]);
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "tower", feature = "axum", feature = "serde_json", feature = "rmp-serde"))]
+    #[tokio::test]
+    async fn tower_tran_layer() -> anyhow::Result<()> {
+        use axum::{Json, Router};
+        use axum::body::{Body, to_bytes};
+        use axum::http::{Request, StatusCode};
+        use axum::routing::post;
+        use tower::ServiceExt;
+        use crate::tower::TranLayer;
+        use crate::{CodecId, Config};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Login {
+            username: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Hello {
+            message: String,
+        }
+
+        async fn login(Json(login): Json<Login>) -> Json<Hello> {
+            Json(Hello { message: format!("hello, {}", login.username) })
+        }
+
+        // not self-describing
+        assert!(TranLayer::new(Config::new()).is_err());
+
+        let config = Config::new().codec(CodecId::MessagePack);
+        let app = Router::new()
+            .route("/login", post(login).layer(TranLayer::new(config.clone())?))
+            .route("/small", post(login).layer(TranLayer::new(config.clone())?.min_size(1024)))
+            .route("/limited", post(login).layer(TranLayer::new(config.clone())?.body_limit(16)));
+        let call = |uri: &'static str, content_type: &'static str, accept: &'static str, body: String| {
+            let app = app.clone();
+            async move {
+                let req = Request::post(uri)
+                    .header("content-type", content_type)
+                    .header("accept", accept)
+                    .body(Body::from(body))?;
+                let resp = app.oneshot(req).await?;
+                let status = resp.status();
+                let content_type = resp.headers().get("content-type").cloned();
+                let body = to_bytes(resp.into_body(), usize::MAX).await?;
+                anyhow::Ok((status, content_type.unwrap(), body))
+            }
+        };
+        let hello = Hello { message: "hello, user".to_string() };
+
+        // envelopes in both directions
+        let body = config.to_json(&Login { username: "user".to_string() })?.to_string()?;
+        let (status, content_type, body) = call("/login", crate::Json::MEDIA_TYPE, crate::Json::MEDIA_TYPE, body).await?;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(config.media_type().to_string(), content_type);
+        assert_eq!(hello, crate::from_json_slice(&body)?.to_value_with::<Hello>(&config)?);
+
+        // plain JSON in both directions
+        let body = r#"{"username":"user"}"#.to_string();
+        let (status, content_type, body) = call("/login", "application/json", "application/json", body).await?;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("application/json", content_type);
+        assert_eq!(hello, serde_json::from_slice::<Hello>(&body)?);
+
+        // plain JSON request, envelope response
+        let body = r#"{"username":"user"}"#.to_string();
+        let (_, content_type, _) = call("/login", "application/json", crate::Json::MEDIA_TYPE, body).await?;
+        assert_eq!(config.media_type().to_string(), content_type);
+
+        // binary envelope response
+        let body = r#"{"username":"user"}"#.to_string();
+        let (_, content_type, body) = call("/login", "application/json", crate::Binary::MEDIA_TYPE, body).await?;
+        assert_eq!(config.binary_media_type().to_string(), content_type);
+        assert_eq!(hello, config.from_slice::<Hello>(&body)?);
+
+        // the response is smaller than min_size
+        let body = r#"{"username":"user"}"#.to_string();
+        let (_, content_type, _) = call("/small", "application/json", crate::Json::MEDIA_TYPE, body).await?;
+        assert_eq!("application/json", content_type);
+
+        // not an envelope
+        let body = r#"{"username":"user"}"#.to_string();
+        let (status, _, body) = call("/login", crate::Json::MEDIA_TYPE, "application/json", body).await?;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!("envelope_parse", body["stage"]);
+
+        // the body limit is responded like the other errors
+        let body = config.to_json(&Login { username: "user".repeat(16) })?.to_string()?;
+        let (status, content_type, body) = call("/limited", crate::Json::MEDIA_TYPE, "application/json", body).await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status);
+        assert_eq!("application/json", content_type);
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!("limit_exceeded", body["code"]);
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "reqwest", feature = "axum", feature = "serde_json"))]
+    #[tokio::test]
+    async fn reqwest_tran() -> anyhow::Result<()> {
+        use axum::Router;
+        use axum::http::StatusCode;
+        use axum::routing::post;
+        use crate::axum::{Negotiate, Tran};
+        use crate::reqwest::{ClientError, RequestBuilderExt, ResponseExt};
+        use crate::MediaType;
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Login {
+            username: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Hello {
+            message: String,
+        }
+
+        async fn login(negotiate: Negotiate, Tran(login): Tran<Login>) -> axum::response::Response {
+            negotiate.respond(&Hello { message: format!("hello, {}", login.username) })
+        }
+
+        async fn plain(axum::Json(login): axum::Json<Login>) -> axum::Json<Hello> {
+            axum::Json(Hello { message: format!("hello, {}", login.username) })
+        }
+
+        // the envelope is sent as application/json
+        async fn envelope(axum::Json(login): axum::Json<Login>) -> axum::Json<crate::Json> {
+            axum::Json(crate::to_json(&Hello { message: format!("hello, {}", login.username) }).unwrap())
+        }
+
+        // the stand-in server
+        let app = Router::new()
+            .route("/login", post(login))
+            .route("/plain", post(plain))
+            .route("/envelope", post(envelope));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+        let login = Login { username: "user".to_string() };
+        let hello = Hello { message: "hello, user".to_string() };
+
+        // the client prefers the binary envelope
+        let resp = client.post(format!("http://{}/login", addr)).tran(&login)?.send().await?;
+        let content_type = resp.headers().get("content-type").and_then(|value| value.to_str().ok());
+        assert!(content_type.and_then(MediaType::from_content_type).is_some_and(|media_type| media_type.is_binary()));
+        assert_eq!(hello, resp.tran::<Hello>().await?);
+
+        // the server responds plain JSON
+        let resp: Hello = client.post(format!("http://{}/plain", addr)).header("content-type", "application/json").body(serde_json::to_vec(&login)?).send().await?.tran().await?;
+        assert_eq!(hello, resp);
+
+        // the server responds an envelope as application/json
+        let resp: Hello = client.post(format!("http://{}/envelope", addr)).header("content-type", "application/json").body(serde_json::to_vec(&login)?).send().await?.tran().await?;
+        assert_eq!(hello, resp);
+        let err = client.post(format!("http://{}/plain", addr)).header("content-type", "application/json").body(serde_json::to_vec(&login)?).send().await?.tran::<u32>().await.unwrap_err();
+        assert!(matches!(&err, ClientError::Tran(err) if err.stage().is_none()), "{}", err);
+
+        // not an envelope
+        let err = client.post(format!("http://{}/login", addr)).header("content-type", "application/json").body(serde_json::to_vec(&login)?).send().await?.tran::<Hello>().await.unwrap_err();
+        assert!(matches!(err, ClientError::Status { status: StatusCode::BAD_REQUEST, .. }));
+
+        // another type
+        let err = client.post(format!("http://{}/login", addr)).tran(&login)?.send().await?.tran::<(String, String)>().await.unwrap_err();
+        assert!(matches!(err, ClientError::Tran(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn media_type_and_accept() -> anyhow::Result<()> {
+        use crate::{Accept, Base, CodecId, MediaType};
+
+        let media_type = MediaType::new().base(Base::Base64).codec(CodecId::MessagePack).compressed(true);
+        assert_eq!("application/vnd.serde-tran+json; base=base64; codec=rmp-serde; compression=gzip", media_type.to_string());
+        assert_eq!(Some(media_type), MediaType::from_content_type(&media_type.to_string()));
+
+        let parsed = MediaType::from_content_type(r#"Application/Vnd.Serde-Tran+Json; charset=utf-8; codec="ciborium""#).unwrap();
+        assert_eq!(MediaType::new().codec(CodecId::Cbor), parsed);
+        assert_eq!(None, MediaType::from_content_type("application/json; codec=bincode"));
+
+        let accept = Accept::parse("application/json;q=0.8, application/vnd.serde-tran+json, text/*;q=0.5, */*;q=0.1");
+        assert!(accept.prefers_tran());
+        assert_eq!(0.8, accept.quality("application/json"));
+        assert_eq!(0.5, accept.quality("text/plain"));
+        assert_eq!(0.1, accept.quality("image/png"));
+
+        assert!(!Accept::parse("application/vnd.serde-tran+json;q=0.5, application/json").prefers_tran());
+        assert!(!Accept::parse("application/vnd.serde-tran+json;q=0").prefers_tran());
+        assert!(!Accept::parse("").prefers_tran());
+        assert_eq!(1.0, Accept::parse("").quality("application/json"));
+        assert!(Accept::parse_all(["application/json;q=0.5", "application/vnd.serde-tran+json"]).prefers_tran());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn to_body_then_from_body() -> anyhow::Result<()> {
+        use crate::{Accept, Binary, Config, MediaType};
+
+        let config = Config::new();
+        let data = (String::from("alice"), 42u32);
+
+        let binary = crate::to_binary(&data)?;
+        assert_eq!(crate::to_vec(&data)?, binary.as_bytes());
+        assert_eq!(data, binary.to_value::<(String, u32)>()?);
+
+        // the client prefers the binary envelopes
+        let accept = Accept::parse("application/vnd.serde-tran, application/vnd.serde-tran+json;q=0.9");
+        assert!(accept.prefers_binary());
+        let (body, media_type) = config.to_body(&data, &accept)?;
+        assert!(media_type.is_binary());
+        assert_eq!(binary.as_bytes(), body);
+        assert_eq!(data, config.from_body::<(String, u32)>(Some(&media_type.to_string()), &body)?);
+
+        // the client prefers the json envelopes
+        let accept = Accept::parse("application/vnd.serde-tran;q=0.5, application/vnd.serde-tran+json");
+        assert!(!accept.prefers_binary());
+        let (body, media_type) = config.to_body(&data, &accept)?;
+        assert!(!media_type.is_binary());
+        assert_eq!(data, config.from_body::<(String, u32)>(Some(&media_type.to_string()), &body)?);
+        assert_eq!(data, config.from_body::<(String, u32)>(None, &body)?);
+
+        assert_eq!(
+            Some(MediaType::binary()),
+            MediaType::from_content_type(Binary::MEDIA_TYPE),
+        );
+        assert_eq!("application/vnd.serde-tran; codec=bincode", MediaType::binary().base(crate::Base::Base64).codec(crate::CodecId::Bincode).to_string());
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "serde_json", feature = "rmp-serde", feature = "ciborium"))]
+    #[test]
+    fn json_in_binary_formats() -> anyhow::Result<()> {
+        use crate::Json;
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Document {
+            name: String,
+            envelope: Json,
+        }
+
+        let data: Vec<u64> = (0..64).collect();
+        let payload = crate::to_vec(&data)?;
+        let document = Document { name: "numbers".to_string(), envelope: crate::to_json(&data)? };
+
+        // human-readable: the basex string
+        let text = serde_json::to_value(&document)?;
+        assert_eq!(document.envelope.data(), text["envelope"]["v"]);
+
+        // binary: the raw payload
+        let msgpack = rmp_serde::to_vec_named(&document)?;
+        assert!(msgpack.windows(payload.len()).any(|window| window == payload));
+        let restored: Document = rmp_serde::from_slice(&msgpack)?;
+        assert_eq!(data, restored.envelope.to_value::<Vec<u64>>()?);
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&document, &mut cbor)?;
+        assert!(cbor.windows(payload.len()).any(|window| window == payload));
+        let restored: Document = ciborium::from_reader(cbor.as_slice())?;
+        assert_eq!(document.envelope.data(), restored.envelope.data());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json_in_binary_formats_of_1_1_0() -> anyhow::Result<()> {
+        use crate::{Base, Json, Tran};
+
+        // the derived layout of Json in 1.1.0, which has the basex string in all formats
+        #[derive(Serialize)]
+        struct LegacyJson {
+            f: Base,
+            v: String,
+        }
+
+        #[derive(Serialize)]
+        struct LegacyDocument {
+            envelope: LegacyJson,
+            code: u16,
+        }
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Document {
+            envelope: Json,
+            code: u16,
+        }
+
+        let data: Vec<u64> = (0..64).collect();
+        let json = crate::to_json(&data)?;
+        let legacy = LegacyDocument { envelope: LegacyJson { f: json.format(), v: json.data().to_string() }, code: 200 };
+
+        let restored: Document = bincode::deserialize(&bincode::serialize(&legacy)?)?;
+        assert_eq!(200, restored.code);
+        assert_eq!(json.data(), restored.envelope.data());
+        assert_eq!(data, restored.envelope.to_value::<Vec<u64>>()?);
+
+        #[cfg(feature = "rmp-serde")]
+        {
+            let restored: Document = rmp_serde::from_slice(&rmp_serde::to_vec_named(&legacy)?)?;
+            assert_eq!(data, restored.envelope.to_value::<Vec<u64>>()?);
+        }
+
+        // the envelopes of now are written with the raw payload, also by Tran without a basex string
+        let bytes = bincode::serialize(&Document { envelope: json.clone(), code: 200 })?;
+        assert_eq!(bytes, bincode::serialize(&(Tran(data.clone()), 200u16))?);
+        let restored: Document = bincode::deserialize(&bytes)?;
+        assert_eq!(json.data(), restored.envelope.data());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn tran_field() -> anyhow::Result<()> {
+        use crate::Tran;
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Payload {
+            names: Vec<String>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct MyResponse {
+            data: Tran<Payload>,
+            code: u16,
+        }
+
+        let response = MyResponse {
+            data: Tran(Payload { names: (0..32).map(|i| format!("name-{}", i)).collect() }),
+            code: 200,
+        };
+
+        let value = serde_json::to_value(&response)?;
+        assert_eq!(200, value["code"]);
+        let json: crate::Json = serde_json::from_value(value["data"].clone())?;
+        assert_eq!(response.data.0, json.to_value::<Payload>()?);
+
+        let restored: MyResponse = serde_json::from_value(value)?;
+        assert_eq!(response, restored);
+
+        // not an envelope
+        let err = serde_json::from_str::<MyResponse>(r#"{"data":{"names":[]},"code":200}"#).unwrap_err();
+        assert!(err.is_data());
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "serde_json", feature = "base64"))]
+    #[test]
+    fn with_adapters() -> anyhow::Result<()> {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Payload {
+            names: Vec<String>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct MyResponse {
+            #[serde(with = "crate::as_json")]
+            data: Payload,
+            #[serde(with = "crate::as_json::option")]
+            extra: Option<Payload>,
+            #[serde(with = "crate::as_json::vec")]
+            pages: Vec<u32>,
+            #[serde(with = "crate::as_base64")]
+            token: (String, u64),
+            #[serde(with = "crate::as_base64::option")]
+            missing: Option<String>,
+            #[serde(with = "crate::as_base64::vec")]
+            roles: Vec<String>,
+        }
+
+        let response = MyResponse {
+            data: Payload { names: (0..32).map(|i| format!("name-{}", i)).collect() },
+            extra: Some(Payload { names: vec!["alice".to_string()] }),
+            pages: vec![1, 2, 3],
+            token: ("alice".to_string(), 42),
+            missing: None,
+            roles: vec!["admin".to_string(), "user".to_string()],
+        };
+
+        let value = serde_json::to_value(&response)?;
+        let json: crate::Json = serde_json::from_value(value["data"].clone())?;
+        assert_eq!(response.data, json.to_value::<Payload>()?);
+        let json: crate::Json = serde_json::from_value(value["extra"].clone())?;
+        assert_eq!(response.extra, Some(json.to_value::<Payload>()?));
+        assert_eq!(3, value["pages"].as_array().map(Vec::len).unwrap_or_default());
+        let token = value["token"].as_str().unwrap_or_default();
+        assert_eq!(response.token, crate::from_base64::<(String, u64)>(token)?);
+        assert!(value["missing"].is_null());
+        assert_eq!(2, value["roles"].as_array().map(Vec::len).unwrap_or_default());
+
+        let restored: MyResponse = serde_json::from_value(value)?;
+        assert_eq!(response, restored);
+
+        // not an envelope
+        #[derive(Debug, Deserialize)]
+        struct Plain {
+            #[serde(with = "crate::as_json")]
+            #[allow(dead_code)]
+            data: Payload,
+        }
+        let err = serde_json::from_str::<Plain>(r#"{"data":{"names":[]}}"#).unwrap_err();
+        assert!(err.is_data());
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "serde_with", feature = "serde_json", feature = "base64"))]
+    #[test]
+    fn with_serde_as() -> anyhow::Result<()> {
+        use std::collections::BTreeMap;
+        use serde_with::serde_as;
+        use crate::{AsBase64, AsJson};
+
+        #[serde_as]
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct MyResponse {
+            #[serde_as(as = "AsJson")]
+            data: Vec<u32>,
+            #[serde_as(as = "Option<AsJson>")]
+            extra: Option<String>,
+            #[serde_as(as = "BTreeMap<_, AsBase64>")]
+            tokens: BTreeMap<String, (String, u64)>,
+        }
+
+        let response = MyResponse {
+            data: (0..100).collect(),
+            extra: None,
+            tokens: [("alice".to_string(), ("admin".to_string(), 42))].into_iter().collect(),
+        };
+
+        let value = serde_json::to_value(&response)?;
+        assert_eq!("base64", value["data"]["f"]);
+        assert!(value["extra"].is_null());
+        assert!(value["tokens"]["alice"].is_string());
+
+        let restored: MyResponse = serde_json::from_value(value)?;
+        assert_eq!(response, restored);
+
+        Ok(())
+    }
 }
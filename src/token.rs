@@ -0,0 +1,139 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::{Base, Config, error};
+use crate::error::{ResultExt, Stage};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// the size of the HMAC-SHA256 at the end of a token
+const MAC_SIZE: usize = 32;
+
+/// [Secret] is the key which signs and verifies the tokens, see [Config::secret]
+#[derive(Clone)]
+pub(crate) struct Secret(Arc<[u8]>);
+
+impl Secret {
+    #[inline]
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.0).expect("HMAC accepts keys of any length")
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+/// the data in a token, with the times (unix time in seconds) it is issued at and expires at
+#[derive(Serialize, Deserialize)]
+struct Claims<T> {
+    issued_at: u64,
+    expires_at: u64,
+    data: T,
+}
+
+#[inline]
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0)
+}
+
+impl Config {
+    /// set the secret which signs the tokens, see [to_token]
+    #[inline]
+    pub fn secret<K: AsRef<[u8]>>(mut self, secret: K) -> Self {
+        self.secret = Some(Secret(Arc::from(secret.as_ref())));
+        self
+    }
+
+    fn secret_or_err(&self) -> error::Result<&Secret> {
+        self.secret.as_ref()
+            .ok_or_else(|| error::ErrorKind::CustomError("secret is not set, see Config::secret".to_string()).into())
+    }
+
+    /// convert data into a token, which expires after ttl, see [to_token].
+    ///
+    /// the time is read from [Config::clock].
+    pub fn to_token<T>(&self, data: &T, ttl: Duration) -> error::Result<String>
+        where T: Serialize + ?Sized
+    {
+        let secret = self.secret_or_err().at(Stage::Encode)?;
+
+        let now = self.clock.now();
+        let claims = Claims {
+            issued_at: unix_time(now),
+            expires_at: now.checked_add(ttl).map(unix_time).unwrap_or(u64::MAX),
+            data,
+        };
+        let mut bytes = self.to_vec(&claims)?;
+
+        let mac = secret.mac().chain_update(&bytes).finalize().into_bytes();
+        bytes.extend_from_slice(&mac);
+
+        crate::to_base(bytes, Base::Base64)
+    }
+
+    /// convert a token back to T, see [from_token]
+    pub fn from_token<T>(&self, token: &str) -> error::Result<T>
+        where T: for<'de> Deserialize<'de>
+    {
+        let secret = self.secret_or_err().at(Stage::Checksum)?;
+
+        let bytes = self.decode_base(token, Base::Base64)?;
+        if bytes.len() < MAC_SIZE {
+            return Err(error::ErrorKind::MacMismatch).at(Stage::Checksum);
+        }
+        let (payload, mac) = bytes.split_at(bytes.len() - MAC_SIZE);
+        secret.mac().chain_update(payload).verify_slice(mac)
+            .map_err(|_| error::ErrorKind::MacMismatch).at(Stage::Checksum)?;
+
+        let claims: Claims<T> = self.from_slice(payload)?;
+        if unix_time(self.clock.now()) >= claims.expires_at {
+            let expires_at = UNIX_EPOCH + Duration::from_secs(claims.expires_at);
+            return Err(error::ErrorKind::Expired(expires_at)).at(Stage::Validate);
+        }
+
+        Ok(claims.data)
+    }
+}
+
+/// convert data into a token (a base64 string, safe in urls), which expires after ttl.
+///
+/// the token is signed with HMAC-SHA256 using the secret, the times it is issued at and expires
+/// at (in seconds) are signed with the data. [from_token] rejects the tokens which are modified
+/// ([error::ErrorKind::MacMismatch]) or expired ([error::ErrorKind::Expired]).
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+/// use serde_tran::{Config, ErrorKind};
+///
+/// let token = serde_tran::to_token(b"secret", "user@example.com", Duration::from_secs(3600)).unwrap();
+/// let email: String = serde_tran::from_token(b"secret", &token).unwrap();
+/// assert_eq!(email, "user@example.com");
+///
+/// // two hours later
+/// let later = SystemTime::now() + Duration::from_secs(7200);
+/// let config = Config::new().secret(b"secret").clock(move || later);
+/// let err = config.from_token::<String>(&token).unwrap_err();
+/// assert!(matches!(err.kind(), ErrorKind::Expired(_)));
+/// ```
+pub fn to_token<K, T>(secret: K, data: &T, ttl: Duration) -> error::Result<String>
+    where
+        K: AsRef<[u8]>,
+        T: Serialize + ?Sized,
+{
+    Config::default().secret(secret).to_token(data, ttl)
+}
+
+/// convert a token (from [to_token]) back to T, the token is verified with the secret
+pub fn from_token<K, T>(secret: K, token: &str) -> error::Result<T>
+    where
+        K: AsRef<[u8]>,
+        T: for<'de> Deserialize<'de>,
+{
+    Config::default().secret(secret).from_token(token)
+}
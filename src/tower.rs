@@ -0,0 +1,233 @@
+//! [tower](https://docs.rs/tower) integration (feature `tower`).
+//!
+//! [TranLayer] converts between plain JSON and [crate::Json] envelopes, so the services of hyper,
+//! axum or any other stack of [http] and [tower_service::Service] exchange envelopes with the
+//! clients which support them, and plain JSON with the others:
+//!
+//! + a request body with the content type [crate::MediaType] is unwrapped into plain JSON
+//!   (`application/json`) before the inner service runs;
+//! + an `application/json` response body is wrapped into an envelope when the request prefers it
+//!   ([Accept::prefers_tran], or [Accept::prefers_binary] for the [crate::Binary] envelopes), see
+//!   [TranLayer::min_size].
+//!
+//! the layer is applied per route (such as `MethodRouter::layer` in axum), each with its own
+//! [Config] and options.
+//!
+//! ```rust,no_run
+//! use axum::{routing::post, Json, Router};
+//! use serde_tran::{CodecId, Config};
+//! use serde_tran::tower::TranLayer;
+//!
+//! async fn hello(Json(name): Json<String>) -> Json<String> {
+//!     Json(format!("hello, {}", name))
+//! }
+//!
+//! let layer = TranLayer::new(Config::new().codec(CodecId::MessagePack)).unwrap().min_size(256);
+//! let app: Router = Router::new().route("/hello", post(hello).layer(layer));
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, Request, Response, StatusCode, header};
+use http_body::Body;
+use http_body_util::{BodyExt, Either, Full, Limited};
+use tower_layer::Layer;
+use tower_service::Service;
+use crate::{Accept, Config, MediaType, error};
+
+/// the body of the requests and responses of [TranService]: the converted body, or the
+/// original one when it is not converted
+pub type TranBody<B> = Either<Full<Bytes>, B>;
+
+/// [TranLayer] is a [Layer] which converts the bodies between plain JSON and [crate::Json]
+/// envelopes in both directions, see [crate::tower].
+///
+/// the layer does not know the types of the services, so the codec of the config should be
+/// self-describing ([crate::CodecId::is_self_describing]), and the clients should use it as well.
+#[derive(Clone, Debug)]
+pub struct TranLayer {
+    inner: Arc<Transcoder>,
+}
+
+#[derive(Clone, Debug)]
+struct Transcoder {
+    config: Config,
+    min_size: usize,
+    body_limit: usize,
+}
+
+impl TranLayer {
+    /// the default of [TranLayer::body_limit], 2 MB
+    pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+    /// create the layer, the codec of config should be self-describing
+    pub fn new(config: Config) -> error::Result<Self> {
+        config.check_self_describing()?;
+
+        Ok(Self {
+            inner: Arc::new(Transcoder {
+                config,
+                min_size: 0,
+                body_limit: Self::DEFAULT_BODY_LIMIT,
+            }),
+        })
+    }
+
+    /// the response bodies smaller than size (in bytes) are not wrapped, because the envelope
+    /// does not save space for them. 0 by default
+    pub fn min_size(mut self, size: usize) -> Self {
+        Arc::make_mut(&mut self.inner).min_size = size;
+        self
+    }
+
+    /// the max size (in bytes) of the request bodies which are unwrapped, the larger ones are
+    /// rejected with 413. [TranLayer::DEFAULT_BODY_LIMIT] by default
+    pub fn body_limit(mut self, limit: usize) -> Self {
+        Arc::make_mut(&mut self.inner).body_limit = limit;
+        self
+    }
+}
+
+impl<S> Layer<S> for TranLayer {
+    type Service = TranService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TranService {
+            inner,
+            transcoder: self.inner.clone(),
+        }
+    }
+}
+
+/// the service of [TranLayer]
+#[derive(Clone, Debug)]
+pub struct TranService<S> {
+    inner: S,
+    transcoder: Arc<Transcoder>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TranService<S>
+    where
+        S: Service<Request<TranBody<ReqBody>>, Response = Response<ResBody>> + Clone + Send + 'static,
+        S::Future: Send,
+        ReqBody: Body<Data = Bytes> + Send + 'static,
+        ReqBody::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        ResBody: Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Response = Response<TranBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        // the clone may not be ready, so the ready one is taken
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let transcoder = self.transcoder.clone();
+
+        Box::pin(async move {
+            let accept = accept_of(req.headers());
+            let accepts = accept.prefers_tran() || accept.prefers_binary();
+
+            let content_type = req.headers().get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| MediaType::from_content_type(value).is_some())
+                .map(str::to_string);
+            let req = if let Some(content_type) = content_type {
+                let (mut parts, body) = req.into_parts();
+                let body = match Limited::new(body, transcoder.body_limit).collect().await {
+                    Ok(body) => body.to_bytes(),
+                    Err(err) => {
+                        let status = match err.is::<http_body_util::LengthLimitError>() {
+                            true => StatusCode::PAYLOAD_TOO_LARGE,
+                            false => StatusCode::BAD_REQUEST,
+                        };
+                        return Ok(json_response(status, error::Error::http_body_of(status.as_u16(), err.to_string())));
+                    },
+                };
+                let body = match transcoder.config.envelope_to_json(Some(&content_type), &body) {
+                    Ok(body) => body,
+                    Err(err) => return Ok(error_response(&err)),
+                };
+
+                parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                parts.headers.remove(header::CONTENT_LENGTH);
+                Request::from_parts(parts, Either::Left(Full::from(body)))
+            } else {
+                req.map(Either::Right)
+            };
+
+            let mut res = inner.call(req).await?;
+            if !is_content_type(res.headers(), "application/json") {
+                return Ok(res.map(Either::Right));
+            }
+
+            // the response depends on the Accept header of the request
+            res.headers_mut().append(header::VARY, HeaderValue::from_static("accept"));
+            let too_small = res.body().size_hint().upper().is_some_and(|size| size < transcoder.min_size as u64);
+            if !accepts || too_small {
+                return Ok(res.map(Either::Right));
+            }
+
+            let (mut parts, body) = res.into_parts();
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(err) => {
+                    let body = error::Error::http_body_of(500, err.into().to_string());
+                    return Ok(json_response(StatusCode::INTERNAL_SERVER_ERROR, body));
+                },
+            };
+            if body.len() < transcoder.min_size {
+                return Ok(Response::from_parts(parts, Either::Left(Full::from(body))));
+            }
+
+            // the body which is not valid JSON is responded as is
+            let body = match transcoder.config.json_to_envelope(&body, &accept) {
+                Ok((wrapped, media_type)) => {
+                    if let Ok(value) = HeaderValue::try_from(media_type.to_string()) {
+                        parts.headers.insert(header::CONTENT_TYPE, value);
+                    }
+                    Bytes::from(wrapped)
+                },
+                Err(_) => body,
+            };
+            parts.headers.remove(header::CONTENT_LENGTH);
+
+            Ok(Response::from_parts(parts, Either::Left(Full::from(body))))
+        })
+    }
+}
+
+/// whether the content type (ignoring the parameters) is media_type
+fn is_content_type(headers: &HeaderMap, media_type: &str) -> bool {
+    headers.get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(media_type))
+}
+
+/// the Accept headers of the request
+fn accept_of(headers: &HeaderMap) -> Accept {
+    Accept::parse_all(headers.get_all(header::ACCEPT).iter().filter_map(|value| value.to_str().ok()))
+}
+
+/// the error response, the body is the `Serialize` of [error::Error]
+fn error_response<B>(err: &error::Error) -> Response<TranBody<B>> {
+    let status = StatusCode::from_u16(err.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    json_response(status, serde_json::to_string(err).unwrap_or_default())
+}
+
+fn json_response<B>(status: StatusCode, body: String) -> Response<TranBody<B>> {
+    let mut res = Response::new(Either::Left(Full::from(body)));
+    *res.status_mut() = status;
+    res.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    res
+}
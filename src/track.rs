@@ -0,0 +1,499 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use serde::Deserialize;
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use crate::{Limit, error};
+
+/// [Path] is the location in the data where decoding failed, such as `orders[3].items[0].price`,
+/// see [crate::Error::path].
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize)]
+/// struct Old { items: Vec<(u32, u32)> }
+/// #[derive(Debug, Deserialize)]
+/// struct New { items: Vec<Item> }
+/// #[derive(Debug, Deserialize)]
+/// struct Item { id: u32, kind: Kind }
+/// #[derive(Debug, Deserialize)]
+/// enum Kind { A, B }
+///
+/// let bytes = serde_tran::to_vec(&Old { items: vec![(1, 0), (2, 5)] }).unwrap();
+/// let err = serde_tran::from_slice::<New>(&bytes).unwrap_err();
+/// assert_eq!(err.path().unwrap().to_string(), "items[1].kind");
+/// ```
+///
+/// note: [crate::Cbor] and [crate::Bitcode] do not report paths.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Path {
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    /// the segments from the outermost to the innermost
+    #[inline]
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.segments.is_empty() {
+            return f.write_str(".");
+        }
+
+        for (idx, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+                _ if idx == 0 => Display::fmt(segment, f)?,
+                _ => write!(f, ".{}", segment)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// [Segment] is one step of a [Path]
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Segment {
+    /// an element of a sequence or a tuple
+    Index(usize),
+    /// a field of a struct, or a key of a map
+    Field(Cow<'static, str>),
+    /// the variant of an enum
+    Variant(Cow<'static, str>),
+    /// a map key or a variant which is not a string or an integer
+    Unknown,
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "[{}]", index),
+            Self::Field(name) | Self::Variant(name) => f.write_str(name),
+            Self::Unknown => f.write_str("?"),
+        }
+    }
+}
+
+/// deserialize T with the deserializer, tracking the [Path] and the depth ([crate::Limits::depth])
+pub(crate) fn deserialize<'de, D, T>(deserializer: D, depth: Option<usize>) -> error::Result<T>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+        error::Error: From<D::Error>,
+{
+    let state = State {
+        max: depth.unwrap_or(usize::MAX),
+        exceeded: Cell::new(false),
+        path: RefCell::new(Vec::new()),
+        failed: RefCell::new(None),
+        key: RefCell::new(None),
+    };
+    let track = Track {
+        remaining: state.max,
+        capture: None,
+        state: &state,
+    };
+
+    let result = TrackSeed { inner: PhantomData::<T>, track }.deserialize(deserializer);
+    result.map_err(|err| {
+        let err = if state.exceeded.get() {
+            error::ErrorKind::LimitExceeded(Limit::Depth(state.max)).into()
+        } else {
+            error::Error::from(err)
+        };
+
+        err.with_path(state.failed.take())
+    })
+}
+
+type Names = Option<&'static [&'static str]>;
+
+/// shared by all the wrappers of one [deserialize] call
+struct State {
+    max: usize,
+    exceeded: Cell<bool>,
+    /// the current path
+    path: RefCell<Vec<Segment>>,
+    /// the path of the innermost failure
+    failed: RefCell<Option<Path>>,
+    /// the last captured map key or variant, `None` if it is not a string or an integer
+    key: RefCell<Option<Cow<'static, str>>>,
+}
+
+#[derive(Clone, Copy)]
+struct Track<'a> {
+    remaining: usize,
+    /// capture the next visited value as a map key or a variant, resolved by the names
+    capture: Option<Names>,
+    state: &'a State,
+}
+
+impl Track<'_> {
+    #[inline]
+    fn enter<E: de::Error>(self) -> Result<Self, E> {
+        if self.remaining == 0 {
+            self.state.exceeded.set(true);
+            return Err(E::custom(Limit::Depth(self.state.max)));
+        }
+
+        Ok(Self {
+            remaining: self.remaining - 1,
+            capture: None,
+            ..self
+        })
+    }
+
+    #[inline]
+    fn capturing(self, names: Names) -> Self {
+        Self {
+            capture: Some(names),
+            ..self
+        }
+    }
+
+    /// run `f` with the segment pushed, the path is recorded if `f` fails
+    #[inline]
+    fn scope<T, E, F: FnOnce() -> Result<T, E>>(self, segment: Segment, f: F) -> Result<T, E> {
+        self.state.path.borrow_mut().push(segment);
+        let result = f();
+        if result.is_err() {
+            let mut failed = self.state.failed.borrow_mut();
+            if failed.is_none() {
+                *failed = Some(Path { segments: self.state.path.borrow().clone() });
+            }
+        }
+        self.state.path.borrow_mut().pop();
+
+        result
+    }
+
+    #[inline]
+    fn capture(&self, key: Option<Key>) {
+        let Some(names) = self.capture else { return };
+
+        let name = key.map(|key| match key {
+            Key::Str(s) => names.and_then(|names| names.iter().find(|name| **name == s))
+                .map(|name| Cow::Borrowed(*name))
+                .unwrap_or_else(|| Cow::Owned(s.to_string())),
+            Key::Index(index) => names.and_then(|names| names.get(index as usize))
+                .map(|name| Cow::Borrowed(*name))
+                .unwrap_or_else(|| Cow::Owned(index.to_string())),
+        });
+        self.state.key.replace(name);
+    }
+
+    #[inline]
+    fn take_key(&self) -> Option<Cow<'static, str>> {
+        self.state.key.take()
+    }
+}
+
+/// a visited value which may be a map key or a variant
+enum Key<'k> {
+    Str(&'k str),
+    Index(u64),
+}
+
+trait AsKey {
+    fn as_key(&self) -> Option<Key<'_>>;
+}
+
+macro_rules! impl_as_key {
+    ($($ty:ty),*) => {
+        $(
+            impl AsKey for $ty {
+                #[inline]
+                fn as_key(&self) -> Option<Key<'_>> {
+                    u64::try_from(*self).ok().map(Key::Index)
+                }
+            }
+        )*
+    };
+}
+
+impl_as_key!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl AsKey for str {
+    #[inline]
+    fn as_key(&self) -> Option<Key<'_>> {
+        Some(Key::Str(self))
+    }
+}
+
+impl AsKey for [u8] {
+    #[inline]
+    fn as_key(&self) -> Option<Key<'_>> {
+        std::str::from_utf8(self).ok().map(Key::Str)
+    }
+}
+
+struct TrackSeed<'a, S> {
+    inner: S,
+    track: Track<'a>,
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for TrackSeed<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner.deserialize(TrackDeserializer { inner: deserializer, track: self.track })
+    }
+}
+
+struct TrackDeserializer<'a, D> {
+    inner: D,
+    track: Track<'a>,
+}
+
+impl<'a, D> TrackDeserializer<'a, D> {
+    #[inline]
+    fn visitor<V>(&self, inner: V) -> TrackVisitor<'a, V> {
+        TrackVisitor { inner, track: self.track, fields: None, variants: None }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error> {
+                let visitor = self.visitor(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for TrackDeserializer<'_, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any(), deserialize_bool(), deserialize_i8(), deserialize_i16(), deserialize_i32(),
+        deserialize_i64(), deserialize_i128(), deserialize_u8(), deserialize_u16(), deserialize_u32(),
+        deserialize_u64(), deserialize_u128(), deserialize_f32(), deserialize_f64(), deserialize_char(),
+        deserialize_str(), deserialize_string(), deserialize_bytes(), deserialize_byte_buf(),
+        deserialize_option(), deserialize_unit(), deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str), deserialize_seq(), deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize), deserialize_map(),
+        deserialize_identifier(), deserialize_ignored_any(),
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = TrackVisitor { fields: Some(fields), ..self.visitor(visitor) };
+        self.inner.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = TrackVisitor { variants: Some(variants), ..self.visitor(visitor) };
+        self.inner.deserialize_enum(name, variants, visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct TrackVisitor<'a, V> {
+    inner: V,
+    track: Track<'a>,
+    /// the field names, when a struct is visited
+    fields: Names,
+    /// the variant names, when an enum is visited
+    variants: Names,
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+macro_rules! forward_visit_key {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                self.track.capture(v.as_key());
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for TrackVisitor<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool), visit_f32(f32), visit_f64(f64), visit_char(char),
+    }
+
+    forward_visit_key! {
+        visit_i8(i8), visit_i16(i16), visit_i32(i32), visit_i64(i64), visit_i128(i128),
+        visit_u8(u8), visit_u16(u16), visit_u32(u32), visit_u64(u64), visit_u128(u128),
+        visit_str(&str), visit_borrowed_str(&'de str), visit_bytes(&[u8]), visit_borrowed_bytes(&'de [u8]),
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        self.track.capture(v.as_str().as_key());
+        self.inner.visit_string(v)
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        self.track.capture(v.as_slice().as_key());
+        self.inner.visit_byte_buf(v)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let track = self.track.enter()?;
+        self.inner.visit_some(TrackDeserializer { inner: deserializer, track })
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let track = self.track.enter()?;
+        self.inner.visit_newtype_struct(TrackDeserializer { inner: deserializer, track })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let track = self.track.enter()?;
+        self.inner.visit_seq(TrackAccess::new(seq, track, self.fields))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let track = self.track.enter()?;
+        self.inner.visit_map(TrackAccess::new(map, track, self.fields))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let track = self.track.enter()?;
+        self.inner.visit_enum(TrackAccess::new(data, track, self.variants))
+    }
+}
+
+/// wraps [SeqAccess], [MapAccess], [EnumAccess] and [VariantAccess]
+struct TrackAccess<'a, A> {
+    inner: A,
+    track: Track<'a>,
+    /// the field names (or the variant names of [EnumAccess])
+    names: Names,
+    /// the index of the next element
+    index: usize,
+    /// the map key of the next value, or the variant
+    key: Option<Cow<'static, str>>,
+}
+
+impl<'a, A> TrackAccess<'a, A> {
+    #[inline]
+    fn new(inner: A, track: Track<'a>, names: Names) -> Self {
+        Self { inner, track, names, index: 0, key: None }
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for TrackAccess<'_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error> {
+        let segment = match self.names.and_then(|names| names.get(self.index)) {
+            Some(name) => Segment::Field(Cow::Borrowed(name)),
+            None => Segment::Index(self.index),
+        };
+        self.index += 1;
+
+        let track = self.track;
+        track.scope(segment, || self.inner.next_element_seed(TrackSeed { inner: seed, track }))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for TrackAccess<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        let track = self.track.capturing(self.names);
+        track.take_key();
+        let key = self.inner.next_key_seed(TrackSeed { inner: seed, track })?;
+        self.key = track.take_key();
+
+        Ok(key)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        let segment = self.key.take().map(Segment::Field).unwrap_or(Segment::Unknown);
+
+        let track = self.track;
+        track.scope(segment, || self.inner.next_value_seed(TrackSeed { inner: seed, track }))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'de, A: EnumAccess<'de>> EnumAccess<'de> for TrackAccess<'a, A> {
+    type Error = A::Error;
+    type Variant = TrackAccess<'a, A::Variant>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let track = self.track.capturing(self.names);
+        track.take_key();
+        let (value, variant) = self.inner.variant_seed(TrackSeed { inner: seed, track })?;
+
+        let mut variant = TrackAccess::new(variant, self.track, None);
+        variant.key = track.take_key();
+        Ok((value, variant))
+    }
+}
+
+impl<'a, A> TrackAccess<'a, A> {
+    #[inline]
+    fn variant(&mut self) -> Segment {
+        self.key.take().map(Segment::Variant).unwrap_or(Segment::Unknown)
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for TrackAccess<'_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(mut self, seed: T) -> Result<T::Value, Self::Error> {
+        let track = self.track;
+        track.scope(self.variant(), || self.inner.newtype_variant_seed(TrackSeed { inner: seed, track }))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(mut self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = TrackVisitor { inner: visitor, track: self.track, fields: None, variants: None };
+        self.track.scope(self.variant(), || self.inner.tuple_variant(len, visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(mut self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = TrackVisitor { inner: visitor, track: self.track, fields: Some(fields), variants: None };
+        self.track.scope(self.variant(), || self.inner.struct_variant(fields, visitor))
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use crate::{CodecId, Config, Extensions, Nonce, error};
+use crate::error::Stage;
+
+/// [Verify] controls whether the `*_verified` functions (such as [Config::to_vec_verified])
+/// decode the payload right after encoding it.
+///
+/// with a codec which is not self-describing (such as [CodecId::Bincode]), asymmetric serde
+/// attributes (for example `#[serde(skip_serializing_if = ...)]`) encode fine, but fail (or
+/// silently produce garbage) when decoding. Verification catches them when encoding.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Verify {
+    /// always verify, the default
+    #[default]
+    Always,
+    /// verify only in debug builds (when `debug_assertions` is enabled)
+    DebugOnly,
+    /// never verify
+    Never,
+}
+
+impl Verify {
+    #[inline]
+    fn enabled(&self) -> bool {
+        match *self {
+            Self::Always => true,
+            Self::DebugOnly => cfg!(debug_assertions),
+            Self::Never => false,
+        }
+    }
+}
+
+/// the way the round trip failed
+enum Failure {
+    /// the payload can not be decoded
+    Decode(error::Error),
+    /// the decoded value is serialized into different bytes
+    Reserialize,
+    /// the decoded value is not equal to the original one
+    NotEqual,
+}
+
+impl Failure {
+    /// the attribute patterns which usually cause the failure
+    fn hint(&self, codec: CodecId) -> &'static str {
+        let self_describing = codec.is_self_describing();
+
+        match self {
+            Self::Decode(err) if !self_describing && Self::is_deserialize_any(err) =>
+                "`#[serde(untagged)]`, `#[serde(flatten)]`, `#[serde(tag = \"...\")]` and `serde_json::Value` \
+                need a self-describing codec, use CodecId::MessagePack or CodecId::Cbor",
+            Self::Decode(_) | Self::Reserialize if !self_describing =>
+                "`#[serde(skip_serializing_if = \"...\")]` and `#[serde(skip_serializing)]` omit fields which \
+                are still expected when decoding, remove them or use a self-describing codec",
+            Self::Decode(_) | Self::Reserialize =>
+                "`#[serde(serialize_with = \"...\")]`, `#[serde(deserialize_with = \"...\")]` or custom \
+                Serialize/Deserialize impls are not symmetric; note that HashMap and HashSet may be \
+                serialized in different orders, use the `*_verified_eq` functions for them",
+            Self::NotEqual =>
+                "`#[serde(skip)]`, `#[serde(skip_deserializing)]` or `#[serde(default)]` drop data, or \
+                `#[serde(skip_serializing_if = \"...\")]` omits fields which are still expected when decoding",
+        }
+    }
+
+    /// whether the codec refuses to decode data whose type is only known from the data
+    fn is_deserialize_any(err: &error::Error) -> bool {
+        match err.kind() {
+            error::ErrorKind::BincodeError(err) => matches!(**err, bincode::ErrorKind::DeserializeAnyNotSupported),
+            #[cfg(feature = "postcard")]
+            error::ErrorKind::PostcardError(err) => matches!(err, postcard::Error::WontImplement),
+            _ => false,
+        }
+    }
+
+    fn into_error<T: ?Sized>(self, codec: CodecId) -> error::Error {
+        let hint = self.hint(codec);
+        let reason = match self {
+            Self::Decode(err) => format!("decoding failed ({})", err),
+            Self::Reserialize => "the decoded value is serialized into different bytes".to_string(),
+            Self::NotEqual => "the decoded value is not equal to the original one".to_string(),
+        };
+
+        error::Error::from(error::ErrorKind::VerifyError(format!(
+            "round trip of `{}` with codec {} failed: {}. hint: {}",
+            std::any::type_name::<T>(), codec.name(), reason, hint,
+        ))).at(Stage::Verify)
+    }
+}
+
+impl Config {
+    /// set when the `*_verified` functions verify the payload, default is [Verify::Always]
+    #[inline]
+    pub fn verify(mut self, verify: Verify) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// convert data to bytes like [Config::to_vec], then decode the bytes and serialize the
+    /// decoded value again, the bytes must be the same.
+    pub fn to_vec_verified<T>(&self, data: &T) -> error::Result<Vec<u8>>
+        where T: Serialize + for<'de> Deserialize<'de>
+    {
+        let bytes = self.to_vec(data)?;
+
+        if self.verify.enabled() {
+            let (decoded, nonce) = self.decode_verified::<T>(&bytes)
+                .map_err(|err| Failure::Decode(err).into_error::<T>(self.codec))?;
+
+            // the decoded value is written with the nonce of the payload, instead of a new one
+            let mut reencoded = Vec::new();
+            self.write_payload(&mut reencoded, &decoded, &Extensions { nonce, ..Extensions::default() })?;
+            if reencoded != bytes {
+                return Err(Failure::Reserialize.into_error::<T>(self.codec));
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// convert data to bytes like [Config::to_vec], then decode the bytes and compare the
+    /// decoded value with data by [PartialEq].
+    pub fn to_vec_verified_eq<T>(&self, data: &T) -> error::Result<Vec<u8>>
+        where T: Serialize + for<'de> Deserialize<'de> + PartialEq
+    {
+        let bytes = self.to_vec(data)?;
+
+        if self.verify.enabled() {
+            let (decoded, _) = self.decode_verified::<T>(&bytes)
+                .map_err(|err| Failure::Decode(err).into_error::<T>(self.codec))?;
+
+            if decoded != *data {
+                return Err(Failure::NotEqual.into_error::<T>(self.codec));
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// decode the payload which is just written like [Config::from_slice], and return its nonce.
+    ///
+    /// [Config::replay_protection] does not apply, so verifying does not record the nonce.
+    fn decode_verified<T>(&self, bytes: &[u8]) -> error::Result<(T, Option<Nonce>)>
+        where T: for<'de> Deserialize<'de>
+    {
+        let config = Config { replay: None, ..self.clone() };
+        config.decode_slice(bytes, |content| Ok((content.decode(&config.limits)?, content.extensions.nonce)))
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// convert given data into [crate::Json], and verify it like [Config::to_vec_verified]
+    pub fn to_json_verified<T>(&self, data: &T) -> error::Result<crate::Json>
+        where T: Serialize + for<'de> Deserialize<'de>
+    {
+        let bytes = self.to_vec_verified(data)?;
+        crate::Json::from_payload(crate::Json::DEFAULT_FORMAT, &bytes)
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// convert given data into [crate::Json], and verify it like [Config::to_vec_verified_eq]
+    pub fn to_json_verified_eq<T>(&self, data: &T) -> error::Result<crate::Json>
+        where T: Serialize + for<'de> Deserialize<'de> + PartialEq
+    {
+        let bytes = self.to_vec_verified_eq(data)?;
+        crate::Json::from_payload(crate::Json::DEFAULT_FORMAT, &bytes)
+    }
+}
+
+/// convert data to bytes, and verify that the bytes can be decoded back.
+/// see [Config::to_vec_verified]
+pub fn to_vec_verified<T>(data: &T) -> error::Result<Vec<u8>>
+    where T: Serialize + for<'de> Deserialize<'de>
+{
+    Config::default().to_vec_verified(data)
+}
+
+/// convert data to bytes, and verify that the bytes are decoded into an equal value.
+/// see [Config::to_vec_verified_eq]
+pub fn to_vec_verified_eq<T>(data: &T) -> error::Result<Vec<u8>>
+    where T: Serialize + for<'de> Deserialize<'de> + PartialEq
+{
+    Config::default().to_vec_verified_eq(data)
+}
+
+#[cfg(feature = "serde_json")]
+/// convert given data into [crate::Json], and verify it. see [Config::to_json_verified]
+pub fn to_json_verified<T>(data: &T) -> error::Result<crate::Json>
+    where T: Serialize + for<'de> Deserialize<'de>
+{
+    Config::default().to_json_verified(data)
+}
+
+#[cfg(feature = "serde_json")]
+/// convert given data into [crate::Json], and verify it. see [Config::to_json_verified_eq]
+pub fn to_json_verified_eq<T>(data: &T) -> error::Result<crate::Json>
+    where T: Serialize + for<'de> Deserialize<'de> + PartialEq
+{
+    Config::default().to_json_verified_eq(data)
+}
@@ -0,0 +1,210 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use serde::{Deserialize, Serialize};
+use crate::{Config, Content, Extensions, Limits, error};
+use crate::error::{ResultExt, Stage};
+
+/// [Versioned] is a type whose schema evolves. The payloads written by the `*_versioned`
+/// functions (such as [Config::to_vec_versioned]) record [Versioned::VERSION], and
+/// [from_slice_versioned] upgrades the payloads of older versions with [Versioned::migrations].
+///
+/// the payloads without a schema version (such as the ones written by [crate::to_vec]) are
+/// treated as version 0. the payloads with a schema version are rejected by [crate::from_slice]
+/// ([error::ErrorKind::VersionedPayload]), decode them with [from_slice_versioned].
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_tran::{Migrations, Versioned};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct UserV1 { name: String }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct UserV2 { name: String, age: u32 }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct User { first_name: String, last_name: String, age: u32 }
+///
+/// impl Versioned for User {
+///     const VERSION: u32 = 3;
+///
+///     fn migrations() -> Migrations<Self> {
+///         Migrations::new(1)
+///             .then(2, |v1: UserV1| UserV2 { name: v1.name, age: 0 })
+///             .then(3, |v2: UserV2| {
+///                 let (first_name, last_name) = v2.name.split_once(' ').unwrap_or((&v2.name, ""));
+///                 User { first_name: first_name.to_string(), last_name: last_name.to_string(), age: v2.age }
+///             })
+///     }
+/// }
+///
+/// // a payload written when User was UserV1
+/// let bytes = serde_tran::Config::new().to_vec_versioned_as(1, &UserV1 { name: "Ada Lovelace".to_string() }).unwrap();
+///
+/// let user: User = serde_tran::from_slice_versioned(&bytes).unwrap();
+/// assert_eq!(user, User { first_name: "Ada".to_string(), last_name: "Lovelace".to_string(), age: 0 });
+/// ```
+pub trait Versioned: Serialize + for<'de> Deserialize<'de> + 'static {
+    /// the current schema version
+    const VERSION: u32;
+
+    /// the decoders of the older versions, and the migrations which upgrade them to the
+    /// current version. by default, no older version is supported.
+    ///
+    /// it is called once per type, the chain is cached for the later payloads.
+    fn migrations() -> Migrations<Self> {
+        Migrations::new(Self::VERSION)
+    }
+}
+
+type Decoder<T> = Box<dyn Fn(&Content<'_>, &Limits) -> error::Result<T> + Send + Sync>;
+
+/// [Migrations] is the chain of schema versions of a [Versioned] type: each version has a
+/// type to decode its payloads, and a migration which upgrades it to the next version.
+pub struct Migrations<T> {
+    decoders: Vec<(u32, Decoder<T>)>,
+}
+
+impl<T> Migrations<T>
+    where T: for<'de> Deserialize<'de> + 'static
+{
+    /// start the chain with the oldest supported version, whose payloads are decoded as T
+    pub fn new(version: u32) -> Self {
+        Self {
+            decoders: vec![(version, Self::decoder())],
+        }
+    }
+
+    /// append the next version, whose payloads are decoded as U. the payloads of the previous
+    /// versions are decoded, and then upgraded to U with migrate.
+    ///
+    /// # Panics
+    ///
+    /// panics if version is not larger than the previous one
+    pub fn then<U, F>(self, version: u32, migrate: F) -> Migrations<U>
+        where
+            U: for<'de> Deserialize<'de> + 'static,
+            F: Fn(T) -> U + Send + Sync + 'static,
+    {
+        let last = self.decoders.last().map(|(version, _)| *version);
+        assert!(last < Some(version), "schema version {} should be larger than {:?}", version, last);
+
+        let migrate = Arc::new(migrate);
+        let mut decoders: Vec<(u32, Decoder<U>)> = self.decoders.into_iter()
+            .map(|(version, decode)| {
+                let migrate = migrate.clone();
+                let decode: Decoder<U> = Box::new(move |content, limits| decode(content, limits).map(&*migrate));
+                (version, decode)
+            })
+            .collect();
+        decoders.push((version, Migrations::<U>::decoder()));
+
+        Migrations { decoders }
+    }
+
+    fn decoder() -> Decoder<T> {
+        Box::new(|content, limits| content.decode(limits))
+    }
+
+    /// decode the payload of the version, and upgrade it to T
+    fn decode(&self, version: u32, content: &Content<'_>, limits: &Limits) -> error::Result<T> {
+        match self.decoders.iter().find(|(v, _)| *v == version) {
+            Some((_, decode)) => decode(content, limits),
+            None => Err(error::ErrorKind::UnknownVersion(std::any::type_name::<T>(), version)).at(Stage::Header),
+        }
+    }
+}
+
+impl<T: Versioned> Migrations<T> {
+    /// the [Versioned::migrations] of T, which are built on the first call
+    fn cached() -> &'static Self {
+        type Cache = RwLock<HashMap<TypeId, &'static (dyn Any + Send + Sync)>>;
+        static CACHE: OnceLock<Cache> = OnceLock::new();
+
+        let cache = CACHE.get_or_init(Cache::default);
+        let cached = cache.read().unwrap_or_else(|err| err.into_inner()).get(&TypeId::of::<T>()).copied();
+        let migrations = match cached {
+            Some(migrations) => migrations,
+            None => {
+                // build the chain without holding the lock, it is leaked once per type
+                let migrations = T::migrations();
+                *cache.write().unwrap_or_else(|err| err.into_inner())
+                    .entry(TypeId::of::<T>())
+                    .or_insert_with(|| Box::leak(Box::new(migrations)))
+            },
+        };
+
+        migrations.downcast_ref().expect("the cache is keyed by the type")
+    }
+}
+
+impl Config {
+    /// convert data to bytes like [Config::to_vec], with [Versioned::VERSION] recorded.
+    /// see [to_vec_versioned]
+    pub fn to_vec_versioned<T>(&self, data: &T) -> error::Result<Vec<u8>>
+        where T: Versioned
+    {
+        self.to_vec_versioned_as(T::VERSION, data)
+    }
+
+    /// convert data to bytes like [Config::to_vec], with the schema version recorded.
+    ///
+    /// it writes the payloads of an older version, for example when some peers are not upgraded yet.
+    pub fn to_vec_versioned_as<T>(&self, version: u32, data: &T) -> error::Result<Vec<u8>>
+        where T: Serialize + ?Sized
+    {
+        let mut bytes = Vec::new();
+        let extensions = Extensions { schema: Some(version), ..Extensions::default() };
+        self.write_payload(&mut bytes, data, &extensions)?;
+
+        Ok(bytes)
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// convert given data into [crate::Json] like [Config::to_json], with [Versioned::VERSION] recorded
+    pub fn to_json_versioned<T>(&self, data: &T) -> error::Result<crate::Json>
+        where T: Versioned
+    {
+        let bytes = self.to_vec_versioned(data)?;
+        crate::Json::from_payload(crate::Json::DEFAULT_FORMAT, &bytes)
+    }
+
+    /// convert bytes into T, the payloads of older versions are upgraded. see [from_slice_versioned]
+    pub fn from_slice_versioned<T>(&self, slice: &[u8]) -> error::Result<T>
+        where T: Versioned
+    {
+        self.decode_slice(slice, |content| {
+            match content.extensions.schema.unwrap_or(0) {
+                version if version == T::VERSION => content.decode(&self.limits),
+                version => Migrations::<T>::cached().decode(version, content, &self.limits),
+            }
+        })
+    }
+}
+
+/// convert data to bytes, with [Versioned::VERSION] recorded, so the bytes can still be decoded
+/// by [from_slice_versioned] after T evolves
+pub fn to_vec_versioned<T>(data: &T) -> error::Result<Vec<u8>>
+    where T: Versioned
+{
+    Config::default().to_vec_versioned(data)
+}
+
+#[cfg(feature = "serde_json")]
+/// convert given data into [crate::Json], with [Versioned::VERSION] recorded. see [to_vec_versioned]
+pub fn to_json_versioned<T>(data: &T) -> error::Result<crate::Json>
+    where T: Versioned
+{
+    Config::default().to_json_versioned(data)
+}
+
+/// convert bytes into T, the schema version is read from the bytes, and the payloads of older
+/// versions are decoded and upgraded with [Versioned::migrations].
+///
+/// [crate::from_slice] rejects the payloads with a schema version, see [error::ErrorKind::VersionedPayload].
+pub fn from_slice_versioned<T>(slice: &[u8]) -> error::Result<T>
+    where T: Versioned
+{
+    Config::default().from_slice_versioned(slice)
+}
@@ -0,0 +1,296 @@
+//! the adapters of `#[serde(with = "...")]`, for the fields which can not be wrapped in [crate::Tran]
+//! (the structs of other crates, the existing public APIs)
+
+use std::marker::PhantomData;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// [AsJson] encodes a field as a [crate::Json] envelope, see [as_json].
+///
+/// with the feature `serde_with`, it is also an adapter of `#[serde_as(as = "...")]`, which
+/// composes with the others (`Option<AsJson>`, `Vec<AsJson>`, `HashMap<_, AsJson>`).
+#[cfg(feature = "serde_json")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsJson;
+
+/// [AsBase64] encodes a field as a base64 string of [crate::to_base64], see [as_base64].
+///
+/// with the feature `serde_with`, it is also an adapter of `#[serde_as(as = "...")]`.
+#[cfg(feature = "base64")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsBase64;
+
+/// the encoding of an adapter
+trait Adapter {
+    fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: Serialize, S: Serializer;
+
+    fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where T: for<'a> Deserialize<'a>, D: Deserializer<'de>;
+}
+
+#[cfg(feature = "serde_json")]
+impl Adapter for AsJson {
+    fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: Serialize, S: Serializer
+    {
+        crate::Json::serialize_value(value, serializer)
+    }
+
+    fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+    {
+        let json = crate::Json::deserialize(deserializer)?;
+        json.to_value().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "base64")]
+impl Adapter for AsBase64 {
+    fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: Serialize, S: Serializer
+    {
+        let s = crate::to_base64(value).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&s)
+    }
+
+    fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+    {
+        let s = String::deserialize(deserializer)?;
+        crate::from_base64(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// serialize the borrowed value with adapter A
+struct Borrowed<'a, A, T>(&'a T, PhantomData<A>);
+
+impl<A: Adapter, T: Serialize> Serialize for Borrowed<'_, A, T> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        A::serialize(self.0, serializer)
+    }
+}
+
+/// deserialize the value with adapter A
+struct Owned<A, T>(T, PhantomData<A>);
+
+impl<'de, A: Adapter, T: for<'a> Deserialize<'a>> Deserialize<'de> for Owned<A, T> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        A::deserialize(deserializer).map(|value| Owned(value, PhantomData))
+    }
+}
+
+fn serialize_option<A, T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where A: Adapter, T: Serialize, S: Serializer
+{
+    match value {
+        Some(value) => serializer.serialize_some(&Borrowed::<A, T>(value, PhantomData)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_option<'de, A, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where A: Adapter, T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+{
+    let value = Option::<Owned<A, T>>::deserialize(deserializer)?;
+    Ok(value.map(|value| value.0))
+}
+
+fn serialize_vec<A, T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where A: Adapter, T: Serialize, S: Serializer
+{
+    serializer.collect_seq(values.iter().map(|value| Borrowed::<A, T>(value, PhantomData)))
+}
+
+fn deserialize_vec<'de, A, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where A: Adapter, T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+{
+    let values = Vec::<Owned<A, T>>::deserialize(deserializer)?;
+    Ok(values.into_iter().map(|value| value.0).collect())
+}
+
+/// `#[serde(with = "serde_tran::as_json")]`: the field is serialized as a [crate::Json] envelope
+/// (with the default [crate::Config]), like [crate::Tran].
+///
+/// `as_json::option` and `as_json::vec` encode each element of `Option<T>` and `Vec<T>`.
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct MyResponse {
+///     #[serde(with = "serde_tran::as_json")]
+///     data: Vec<u32>,
+///     #[serde(with = "serde_tran::as_json::option")]
+///     extra: Option<String>,
+///     code: u16,
+/// }
+///
+/// let response = MyResponse { data: (0..100).collect(), extra: None, code: 200 };
+/// let text = serde_json::to_string(&response).unwrap();
+/// assert!(text.starts_with(r#"{"data":{"f":"base64","v":""#));
+///
+/// let restored: MyResponse = serde_json::from_str(&text).unwrap();
+/// assert_eq!(restored.data.len(), 100);
+/// ```
+#[cfg(feature = "serde_json")]
+pub mod as_json {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::{Adapter, AsJson};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: Serialize, S: Serializer
+    {
+        AsJson::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+    {
+        AsJson::deserialize(deserializer)
+    }
+
+    /// `Option<T>`, the `Some` value is serialized as a [crate::Json] envelope
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use super::AsJson;
+
+        pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+            where T: Serialize, S: Serializer
+        {
+            super::super::serialize_option::<AsJson, _, _>(value, serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+            where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+        {
+            super::super::deserialize_option::<AsJson, _, _>(deserializer)
+        }
+    }
+
+    /// `Vec<T>`, each element is serialized as a [crate::Json] envelope
+    pub mod vec {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use super::AsJson;
+
+        pub fn serialize<T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+            where T: Serialize, S: Serializer
+        {
+            super::super::serialize_vec::<AsJson, _, _>(values, serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+            where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+        {
+            super::super::deserialize_vec::<AsJson, _, _>(deserializer)
+        }
+    }
+}
+
+/// `#[serde(with = "serde_tran::as_base64")]`: the field is serialized as a string of
+/// [crate::to_base64] (with the default [crate::Config]).
+///
+/// `as_base64::option` and `as_base64::vec` encode each element of `Option<T>` and `Vec<T>`.
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Session {
+///     #[serde(with = "serde_tran::as_base64::vec")]
+///     roles: Vec<(String, u32)>,
+/// }
+///
+/// let session = Session { roles: vec![("admin".to_string(), 1)] };
+/// let text = serde_json::to_string(&session).unwrap();
+/// assert!(text.starts_with(r#"{"roles":[""#));
+///
+/// let restored: Session = serde_json::from_str(&text).unwrap();
+/// assert_eq!(restored.roles, session.roles);
+/// ```
+#[cfg(feature = "base64")]
+pub mod as_base64 {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::{Adapter, AsBase64};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: Serialize, S: Serializer
+    {
+        AsBase64::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+    {
+        AsBase64::deserialize(deserializer)
+    }
+
+    /// `Option<T>`, the `Some` value is serialized as a base64 string
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use super::AsBase64;
+
+        pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+            where T: Serialize, S: Serializer
+        {
+            super::super::serialize_option::<AsBase64, _, _>(value, serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+            where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+        {
+            super::super::deserialize_option::<AsBase64, _, _>(deserializer)
+        }
+    }
+
+    /// `Vec<T>`, each element is serialized as a base64 string
+    pub mod vec {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use super::AsBase64;
+
+        pub fn serialize<T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+            where T: Serialize, S: Serializer
+        {
+            super::super::serialize_vec::<AsBase64, _, _>(values, serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+            where T: for<'a> Deserialize<'a>, D: Deserializer<'de>
+        {
+            super::super::deserialize_vec::<AsBase64, _, _>(deserializer)
+        }
+    }
+}
+
+#[cfg(all(feature = "serde_with", feature = "serde_json"))]
+impl<T: Serialize> serde_with::SerializeAs<T> for AsJson {
+    #[inline]
+    fn serialize_as<S: Serializer>(source: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        <Self as Adapter>::serialize(source, serializer)
+    }
+}
+
+#[cfg(all(feature = "serde_with", feature = "serde_json"))]
+impl<'de, T: for<'a> Deserialize<'a>> serde_with::DeserializeAs<'de, T> for AsJson {
+    #[inline]
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        <Self as Adapter>::deserialize(deserializer)
+    }
+}
+
+#[cfg(all(feature = "serde_with", feature = "base64"))]
+impl<T: Serialize> serde_with::SerializeAs<T> for AsBase64 {
+    #[inline]
+    fn serialize_as<S: Serializer>(source: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        <Self as Adapter>::serialize(source, serializer)
+    }
+}
+
+#[cfg(all(feature = "serde_with", feature = "base64"))]
+impl<'de, T: for<'a> Deserialize<'a>> serde_with::DeserializeAs<'de, T> for AsBase64 {
+    #[inline]
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        <Self as Adapter>::deserialize(deserializer)
+    }
+}